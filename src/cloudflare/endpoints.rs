@@ -4,9 +4,10 @@ use crate::cloudflare::models::{
 };
 use crate::cloudflare::requests;
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::fmt::Display;
-use tracing::debug;
+use tracing::{debug, info};
 
 /// Return a list of login messages if the token is verifiable.
 pub async fn verify(token: &str) -> Result<Vec<CloudflareMessage>> {
@@ -45,10 +46,24 @@ pub async fn zones(token: impl Display) -> Result<Vec<Zone>> {
     Ok(zones)
 }
 
-/// Return all known Cloudflare records.
+/// Return all known Cloudflare A and AAAA records, which cddns can manage.
 pub async fn records(
     zones: &Vec<Zone>,
     token: impl Display,
+) -> Result<Vec<Record>> {
+    let mut records = all_records(zones, token).await?;
+    records.retain(|record| {
+        record.record_type == "A"
+            || record.record_type == "AAAA" && !record.locked
+    });
+    debug!("collected {} A and AAAA records", records.len());
+    Ok(records)
+}
+
+/// Return all known Cloudflare records, of every type.
+pub async fn all_records(
+    zones: &Vec<Zone>,
+    token: impl Display,
 ) -> Result<Vec<Record>> {
     let mut records = vec![];
     for zone in zones {
@@ -66,10 +81,7 @@ pub async fn records(
                     .await
                     .context("error resolving records endpoint")?;
 
-            records.extend(resp.result.into_iter().filter(|record| {
-                record.record_type == "A"
-                    || record.record_type == "AAAA" && !record.locked
-            }));
+            records.extend(resp.result);
 
             page_cursor += 1;
             if page_cursor > resp.result_info.total_pages {
@@ -86,13 +98,24 @@ pub async fn records(
     Ok(records)
 }
 
-/// Patch a Cloudflare record.
+/// Patch a Cloudflare record. Returns whether the record was updated, which
+/// it is not in a dry run.
 pub async fn update_record(
     token: impl Display,
     zone_id: impl Display,
     record_id: impl Display,
     ip: impl Display,
-) -> Result<()> {
+    dry_run: bool,
+) -> Result<bool> {
+    if dry_run {
+        info!(
+            zone = %zone_id,
+            id = %record_id,
+            ip = %ip,
+            "dry run: would update record"
+        );
+        return Ok(false);
+    }
     let endpoint = format!("/zones/{zone_id}/dns_records/{record_id}");
 
     let mut data = HashMap::new();
@@ -101,5 +124,32 @@ pub async fn update_record(
     requests::patch_with_timeout::<PatchRecordResponse>(endpoint, token, &data)
         .await
         .context("error resolving records endpoint")?;
-    Ok(())
+    Ok(true)
+}
+
+/// Patch the given fields of a Cloudflare record, returning the result, or
+/// nothing in a dry run.
+pub async fn patch_record(
+    token: impl Display,
+    zone_id: impl Display,
+    record_id: impl Display,
+    fields: &(impl Serialize + ?Sized),
+    dry_run: bool,
+) -> Result<Option<Record>> {
+    if dry_run {
+        info!(
+            zone = %zone_id,
+            id = %record_id,
+            "dry run: would patch record"
+        );
+        return Ok(None);
+    }
+    let endpoint = format!("/zones/{zone_id}/dns_records/{record_id}");
+
+    let resp = requests::patch_with_timeout::<PatchRecordResponse>(
+        endpoint, token, fields,
+    )
+    .await
+    .context("error resolving records endpoint")?;
+    Ok(Some(resp.result))
 }
@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
 
 #[derive(Debug, Deserialize)]
@@ -44,7 +44,7 @@ pub struct ResultInfo {
     pub total_pages: i32,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Zone {
     pub id: String,
     pub name: String,
@@ -58,7 +58,7 @@ impl fmt::Display for Zone {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
     pub id: String,
     pub zone_id: String,
@@ -68,6 +68,10 @@ pub struct Record {
     pub record_type: String,
     pub content: String,
     pub locked: bool,
+    /// The TTL in seconds, where 1 is automatic.
+    pub ttl: u32,
+    #[serde(default)]
+    pub proxied: bool,
 }
 
 impl fmt::Display for Record {
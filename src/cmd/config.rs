@@ -1,10 +1,17 @@
+use crate::config::edit::{split_key, ConfigEditor};
+use crate::config::layers::{env_var, ConfigLayers, Source};
+use crate::config::models::redact;
 use crate::config::{default_config_path, models::ConfigOpts};
 use crate::inventory::default_inventory_path;
+use crate::inventory::lint::Severity;
 use crate::util;
+use crate::util::fs::RestoreOpts;
 use crate::util::scanner::{prompt, prompt_ron, prompt_t, prompt_yes_or_no};
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::{Args, Subcommand};
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tracing::info;
 
 /// Configuration controls
 #[derive(Debug, Args)]
@@ -16,26 +23,130 @@ pub struct ConfigCmd {
 
 #[derive(Clone, Debug, Subcommand)]
 enum ConfigSubcommands {
-    /// Build a configuration file.
+    /// Build a configuration file, or a profile of it with `--profile`.
     Build,
     /// Show the current configuration.
-    Show,
+    Show(ShowOpts),
+    /// Check the configuration for unknown keys and invalid values.
+    Check,
+    /// Print the effective value of a key, e.g. `inventory.watch_interval`.
+    Get(GetOpts),
+    /// Set a key in the configuration file, keeping other content.
+    Set(SetOpts),
+    /// Remove a key from the configuration file, keeping other content.
+    Unset(UnsetOpts),
+    /// Restore the configuration file from a backup.
+    Restore(RestoreOpts),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ShowOpts {
+    /// Show secrets, such as the token, rather than masking them.
+    #[clap(long)]
+    pub reveal: bool,
+    /// Print the effective configuration in this format. [default: each
+    /// option with the layer it comes from]
+    #[clap(long, value_enum, value_name = "format")]
+    pub output: Option<ShowFormat>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GetOpts {
+    /// The dotted key to print, e.g. `inventory.watch_interval`.
+    #[clap(value_name = "key")]
+    pub key: String,
+    /// Show secrets, such as the token, rather than masking them.
+    #[clap(long)]
+    pub reveal: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SetOpts {
+    /// The dotted key to set, e.g. `inventory.watch_interval`.
+    #[clap(value_name = "key")]
+    pub key: String,
+    /// The value, as TOML (e.g. `30000`, `true` or `["a", "b"]`) or a string.
+    #[clap(value_name = "value", allow_hyphen_values = true)]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct UnsetOpts {
+    /// The dotted key to remove, e.g. `inventory.watch_interval`.
+    #[clap(value_name = "key")]
+    pub key: String,
+}
+
+/// The formats the configuration can be printed as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ShowFormat {
+    Toml,
+    Json,
 }
 
 impl ConfigCmd {
+    /// Returns whether this may create the selected profile, which need not
+    /// exist yet.
+    pub fn creates_profile(&self) -> bool {
+        matches!(
+            self.action,
+            ConfigSubcommands::Build | ConfigSubcommands::Set(_)
+        )
+    }
+
+    /// Returns whether this checks the config file, which should then be
+    /// read leniently so its problems can be reported.
+    pub fn checks_config(&self) -> bool {
+        matches!(self.action, ConfigSubcommands::Check)
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn run(self, opts: ConfigOpts) -> Result<()> {
+    pub async fn run(
+        self,
+        opts: ConfigOpts,
+        layers: ConfigLayers,
+        path: Option<PathBuf>,
+        profile: Option<String>,
+    ) -> Result<()> {
         match self.action {
-            ConfigSubcommands::Build => build().await,
-            ConfigSubcommands::Show => show(&opts).await,
+            ConfigSubcommands::Build => build(&opts, profile.as_deref()).await,
+            ConfigSubcommands::Show(show_opts) => {
+                show(&opts, &layers, &show_opts).await
+            }
+            ConfigSubcommands::Check => {
+                let path = path.unwrap_or_else(default_config_path);
+                check(&opts, &path).await
+            }
+            ConfigSubcommands::Get(get_opts) => get(&opts, &get_opts).await,
+            ConfigSubcommands::Set(set_opts) => {
+                let path = path.unwrap_or_else(default_config_path);
+                set(&opts, &path, profile.as_deref(), &set_opts).await
+            }
+            ConfigSubcommands::Unset(unset_opts) => {
+                let path = path.unwrap_or_else(default_config_path);
+                unset(&opts, &path, profile.as_deref(), &unset_opts).await
+            }
+            ConfigSubcommands::Restore(restore_opts) => {
+                let path = path.unwrap_or_else(default_config_path);
+                util::fs::restore(
+                    &path,
+                    &restore_opts,
+                    opts.inventory.backups.unwrap_or_default(),
+                    opts.inventory.dry_run.unwrap_or_default(),
+                )
+                .await
+            }
         }
     }
 }
 
-#[tracing::instrument(level = "trace")]
-async fn build() -> Result<()> {
+#[tracing::instrument(level = "trace", skip_all)]
+async fn build(opts: &ConfigOpts, profile: Option<&str>) -> Result<()> {
     // Prompt
     println!("Welcome! This builder will build a CLI configuration file without needing to understand TOML.");
+    if let Some(name) = profile {
+        println!("Your answers will be saved as the profile '{name}', overlaying the base configuration when selected.");
+    }
     println!("For annotated examples of each field, please visit https://github.com/simbleau/cddns/blob/main/config.toml");
     println!("You can skip any answer for cddns' defaults, which may change over time.");
 
@@ -51,11 +162,11 @@ async fn build() -> Result<()> {
         })
         .list_include_zones({
             println!();
-            println!(r#"Next, if you want filtered ZONE output in the CLI, provide regex filters in RON notation which will INCLUDE output in `cddns inventory build` and `cddns list`."#);
+            println!(r#"Next, if you want filtered ZONE output in the CLI, provide regex or `glob:` filters in RON notation, any of which will INCLUDE output in `cddns inventory build` and `cddns list`."#);
             println!(r#" > what is RON? https://github.com/ron-rs/ron/wiki/Specification"#);
             println!(r#" > what are zones? https://www.cloudflare.com/learning/dns/glossary/dns-zone/"#);
             println!(r#" > examples: [], [".*.(com|dev)"], ["example.com", "example.dev"]"#);
-            println!(r#" > default: [".*"] (all)"#);
+            println!(r#" > default: [] (all)"#);
             prompt_ron(
                 "include zone filters",
                 "list[string]",
@@ -63,7 +174,7 @@ async fn build() -> Result<()> {
         })
         .list_ignore_zones({
             println!();
-            println!(r#"Next, if you want filtered ZONE output in the CLI, provide regex filters in RON notation which will IGNORE output in `cddns inventory build` and `cddns list`."#);
+            println!(r#"Next, if you want filtered ZONE output in the CLI, provide regex or `glob:` filters in RON notation, any of which will IGNORE output in `cddns inventory build` and `cddns list`."#);
             println!(r#" > what is RON? https://github.com/ron-rs/ron/wiki/Specification"#);
             println!(r#" > what are zones? https://www.cloudflare.com/learning/dns/glossary/dns-zone/"#);
             println!(r#" > examples: [], [".*.(com|dev)"], ["example.com", "example.dev"]"#);
@@ -75,11 +186,11 @@ async fn build() -> Result<()> {
         })
         .list_include_records({
             println!();
-            println!(r#"Next, if you want filtered RECORD output in the CLI, provide regex filters in RON notation which will INCLUDE output in `cddns inventory build` and `cddns list`."#);
+            println!(r#"Next, if you want filtered RECORD output in the CLI, provide regex or `glob:` filters in RON notation, any of which will INCLUDE output in `cddns inventory build` and `cddns list`."#);
             println!(r#" > what is RON? https://github.com/ron-rs/ron/wiki/Specification"#);
             println!(r#" > what are records? https://www.cloudflare.com/learning/dns/dns-records/"#);
             println!(r#" > examples: [], [".*.example.com"], ["beta.example.com", "gamma.example.com"]"#);
-            println!(r#" > default: [".*"] (all)"#);
+            println!(r#" > default: [] (all)"#);
             prompt_ron(
                 "include record filters",
                 "list[string]",
@@ -87,7 +198,7 @@ async fn build() -> Result<()> {
         })
         .list_ignore_records({
             println!();
-            println!(r#"Next, if you want filtered RECORD output in the CLI, provide regex filters in RON notation which will IGNORE output in `cddns inventory build` and `cddns list`."#);
+            println!(r#"Next, if you want filtered RECORD output in the CLI, provide regex or `glob:` filters in RON notation, any of which will IGNORE output in `cddns inventory build` and `cddns list`."#);
             println!(r#" > what is RON? https://github.com/ron-rs/ron/wiki/Specification"#);
             println!(r#" > what are records? https://www.cloudflare.com/learning/dns/dns-records/"#);
             println!(r#" > examples: [], [".*.example.com"], ["beta.example.com", "gamma.example.com"]"#);
@@ -100,6 +211,21 @@ async fn build() -> Result<()> {
             println!(r#" > default: {}"#, default_inventory_path().display());
             prompt_t("inventory path", "path")?
         })
+        .inventory_format({
+            println!();
+            println!(r#"Next, specify the format of your DNS inventory file."#);
+            println!(r#" > options: yaml, json, toml"#);
+            println!(r#" > default: detected from the file extension"#);
+            prompt_t("inventory format", "format")?
+        })
+        .inventory_include({
+            println!();
+            println!(r#"Next, if your inventory is split into fragments, provide the files, directories or globs to merge in RON notation. An `inventory.d` directory beside your inventory file is always included."#);
+            println!(r#" > what is RON? https://github.com/ron-rs/ron/wiki/Specification"#);
+            println!(r#" > examples: [], ["/etc/cddns/teams"], ["web/*.yml", "vpn.toml"]"#);
+            println!(r#" > default: [] (none)"#);
+            prompt_ron("inventory includes", "list[string]")?
+        })
         .inventory_force_update({
             println!();
             println!(r#"Next, would you like to skip the prompt (force) when using the `inventory update` command?"#);
@@ -121,6 +247,16 @@ async fn build() -> Result<()> {
                 "interval for `inventory watch`?",
                 "number",
             )?
+        })
+        .inventory_backups({
+            println!();
+            println!(r#"Next, specify how many timestamped backups to keep when cddns overwrites a file, including inventory and config files."#);
+            println!(r#" > examples: 0 (none), 10"#);
+            println!(r#" > default: 3"#);
+            prompt_t(
+                "backups to keep?",
+                "number",
+            )?
         });
 
     // Save
@@ -135,13 +271,185 @@ async fn build() -> Result<()> {
             })
             .unwrap_or(default_config_path())
     };
-    util::fs::remove_interactive(&path).await?;
-    builder.save(path).await?;
+    let backups = opts.inventory.backups.unwrap_or_default();
+    let dry_run = opts.inventory.dry_run.unwrap_or_default();
+    match profile {
+        // Edit the profile in place, keeping the rest of the file
+        Some(name) => {
+            let text = match path.exists() {
+                true => std::fs::read_to_string(&path)
+                    .context("reading config file")?,
+                false => String::new(),
+            };
+            let mut editor = ConfigEditor::parse(&text)?;
+            if editor.has_profile(name) {
+                let overwrite = prompt_yes_or_no(
+                    format!(
+                        "Profile '{name}' exists in '{}', overwrite?",
+                        path.display()
+                    ),
+                    "y/N",
+                )?
+                .unwrap_or(false);
+                if !overwrite {
+                    bail!("aborted")
+                }
+            }
+            editor.set_profile(name, &builder)?;
+            util::fs::save(&path, editor.to_string(), backups, dry_run).await?;
+        }
+        None => {
+            util::fs::overwrite_interactive(&path).await?;
+            builder.save(path, backups, dry_run).await?;
+        }
+    }
 
     Ok(())
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
-async fn show(opts: &ConfigOpts) -> Result<()> {
-    Ok(println!("{opts}"))
+async fn show(
+    opts: &ConfigOpts,
+    layers: &ConfigLayers,
+    cli_opts: &ShowOpts,
+) -> Result<()> {
+    let mut opts = opts.clone();
+    if !cli_opts.reveal {
+        opts.verify.token = opts.verify.token.as_deref().map(redact);
+    }
+    let mut printed = String::new();
+    match cli_opts.output {
+        Some(ShowFormat::Toml) => {
+            printed = crate::util::encoding::as_toml(&opts)?;
+        }
+        Some(ShowFormat::Json) => {
+            printed = crate::util::encoding::as_json(&opts)? + "\n";
+        }
+        None => {
+            if let Some(name) = layers.profile_name.as_deref() {
+                let _ = writeln!(printed, "Profile: {name}");
+            }
+            for (label, key, value) in opts.entries(true) {
+                let source = match layers.source(key) {
+                    Some(Source::Env) => format!("env {}", env_var(key)),
+                    Some(Source::Profile) => match &layers.profile_name {
+                        Some(name) => format!("profile {name}"),
+                        None => Source::Profile.to_string(),
+                    },
+                    Some(source) => source.to_string(),
+                    None => "unset".to_string(),
+                };
+                let _ = writeln!(printed, "{label}: {value} ({source})");
+            }
+        }
+    }
+    util::stdout::print(printed)
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+async fn check(opts: &ConfigOpts, path: &Path) -> Result<()> {
+    let issues = crate::config::check::check(opts, path);
+    for issue in issues.iter() {
+        println!("{issue}");
+    }
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    info!(errors, warnings, "summary");
+    if errors > 0 {
+        bail!(
+            "config check failed with {errors} errors and {warnings} warnings"
+        );
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+async fn get(opts: &ConfigOpts, cli_opts: &GetOpts) -> Result<()> {
+    let (section, field) = split_key(&cli_opts.key)?;
+    let mut opts = opts.clone();
+    if !cli_opts.reveal {
+        opts.verify.token = opts.verify.token.as_deref().map(redact);
+    }
+    let value = serde_json::to_value(&opts)?
+        .pointer(&format!("/{section}/{field}"))
+        .cloned()
+        .unwrap_or_default();
+    match value {
+        serde_json::Value::Null => bail!("'{}' is not set", cli_opts.key),
+        serde_json::Value::String(value) => {
+            util::stdout::print(format!("{value}\n"))
+        }
+        value => {
+            util::stdout::print(format!("{}\n", toml::Value::try_from(value)?))
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+async fn set(
+    opts: &ConfigOpts,
+    path: &Path,
+    profile: Option<&str>,
+    cli_opts: &SetOpts,
+) -> Result<()> {
+    let text = match path.exists() {
+        true => std::fs::read_to_string(path).context("reading config file")?,
+        false => String::new(),
+    };
+    let mut editor = ConfigEditor::parse(&text)?;
+    editor.set(profile, &cli_opts.key, &cli_opts.value)?;
+    util::fs::save(
+        path,
+        editor.to_string(),
+        opts.inventory.backups.unwrap_or_default(),
+        opts.inventory.dry_run.unwrap_or_default(),
+    )
+    .await?;
+    match profile {
+        Some(name) => info!(
+            "set '{}' in profile '{name}' of '{}'",
+            cli_opts.key,
+            path.display()
+        ),
+        None => info!("set '{}' in '{}'", cli_opts.key, path.display()),
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+async fn unset(
+    opts: &ConfigOpts,
+    path: &Path,
+    profile: Option<&str>,
+    cli_opts: &UnsetOpts,
+) -> Result<()> {
+    if !path.exists() {
+        bail!("config file '{}' does not exist", path.display());
+    }
+    let text = std::fs::read_to_string(path).context("reading config file")?;
+    let mut editor = ConfigEditor::parse(&text)?;
+    if !editor.unset(profile, &cli_opts.key)? {
+        match profile {
+            Some(name) => bail!(
+                "'{}' is not set in profile '{name}' of '{}'",
+                cli_opts.key,
+                path.display()
+            ),
+            None => {
+                bail!("'{}' is not set in '{}'", cli_opts.key, path.display())
+            }
+        }
+    }
+    util::fs::save(
+        path,
+        editor.to_string(),
+        opts.inventory.backups.unwrap_or_default(),
+        opts.inventory.dry_run.unwrap_or_default(),
+    )
+    .await?;
+    info!("unset '{}' in '{}'", cli_opts.key, path.display());
+    Ok(())
 }
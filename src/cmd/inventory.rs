@@ -1,15 +1,31 @@
 use crate::cloudflare::{self, endpoints::update_record, models::Record};
+use crate::config::default_config_path;
+use crate::config::edit::ConfigEditor;
 use crate::config::models::{ConfigOpts, ConfigOptsInventory};
 use crate::inventory::default_inventory_path;
-use crate::inventory::models::{Inventory, InventoryData};
+use crate::inventory::export::ExportFormat;
+use crate::inventory::import::ImportFormat;
+use crate::inventory::lint::{Linter, Severity};
+use crate::inventory::models::{
+    Inventory, InventoryData, InventoryFormat, InventoryRecord, InventorySet,
+};
+use crate::inventory::plan::{Plan, PlannedChange};
+use crate::inventory::report::{
+    Report, ReportEntry, ReportFailure, ReportFormat, ReportRecord,
+};
 use crate::util;
+use crate::util::fs::RestoreOpts;
+use crate::util::ip::PublicIps;
 use crate::util::scanner::{prompt_t, prompt_yes_or_no};
-use anyhow::{Context, Result};
+use crate::util::table::Table;
+use crate::util::tui::{InventoryTui, TuiOutcome};
+use anyhow::{bail, Context, Result};
 use clap::{Args, Subcommand};
-use std::collections::HashSet;
-use std::fmt::Debug;
-use std::net::{Ipv4Addr, Ipv6Addr};
-use std::path::PathBuf;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use tokio::time::{self, Duration, MissedTickBehavior};
 use tracing::{debug, error, info, trace, warn};
 
@@ -27,16 +43,31 @@ pub struct InventoryCmd {
 enum InventorySubcommands {
     /// Build an inventory file.
     Build(BuildOpts),
+    /// Build an inventory file from another DDNS tool's configuration.
+    Import(ImportOpts),
+    /// Print managed DNS records as BIND, Terraform or octoDNS records.
+    Export(ExportOpts),
     /// Print your inventory.
     Show(ShowOpts),
+    /// Report mistakes in the inventory, without network access.
+    Lint(LintOpts),
     /// Print erroneous DNS records.
-    Check,
+    Check(ReportOpts),
     /// Update outdated DNS records present in the inventory.
-    Update,
+    Update(ReportOpts),
+    /// Print the changes `update` would make, optionally saving them to a
+    /// plan file.
+    Plan(PlanOpts),
+    /// Make the changes in a plan file, unless records changed since.
+    Apply(ApplyOpts),
     /// Prune invalid DNS records present in the inventory.
-    Prune,
+    Prune(ReportOpts),
+    /// Rewrite the ids of records which were re-linked by name and type.
+    Repair(RepairOpts),
     /// Continuously update DNS records on an interval.
-    Watch,
+    Watch(SelectOpts),
+    /// Restore the inventory file from a backup.
+    Restore(RestoreOpts),
 }
 
 #[derive(Debug, Clone, Args)]
@@ -47,6 +78,32 @@ pub struct BuildOpts {
     /// Output the inventory without post-processing.
     #[clap(long)]
     pub clean: bool,
+    /// Select records in a full-screen terminal UI.
+    #[clap(long)]
+    pub tui: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ImportOpts {
+    /// The tool whose configuration is imported.
+    #[clap(long, value_enum, value_name = "format")]
+    pub from: ImportFormat,
+    /// The configuration file to import.
+    #[clap(value_name = "file")]
+    pub file: PathBuf,
+    /// Print the inventory to stdout, instead of saving the file.
+    #[clap(long)]
+    pub stdout: bool,
+    /// Output the inventory without post-processing.
+    #[clap(long)]
+    pub clean: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ExportOpts {
+    /// The format to export records as.
+    #[clap(long, value_enum, value_name = "format")]
+    pub format: ExportFormat,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -54,11 +111,111 @@ pub struct ShowOpts {
     /// Output the inventory without post-processing.
     #[clap(long)]
     pub clean: bool,
+    /// Review and edit the inventory in a full-screen terminal UI.
+    #[clap(long)]
+    pub tui: bool,
+    /// Output the inventory with environment variables and `{hostname}`
+    /// expanded.
+    #[clap(long)]
+    pub expanded: bool,
+    /// Follow the inventory with the status of each record against the
+    /// public IP.
+    #[clap(long)]
+    pub status: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LintOpts {
+    /// Also resolve entries against Cloudflare.
+    #[clap(long)]
+    pub online: bool,
+    /// Fail on warnings, as well as errors.
+    #[clap(long)]
+    pub strict: bool,
+}
+
+/// Selectors for a subset of the inventory. Each selector matches any of its
+/// values, and records must match every selector given.
+#[derive(Debug, Clone, Default, Args)]
+pub struct SelectOpts {
+    /// Select records tagged with one of these groups.
+    #[clap(long, value_name = "group1,group2,..", value_delimiter = ',')]
+    pub group: Vec<String>,
+    /// Select records in one of these zones, by id or name.
+    #[clap(long, value_name = "zone1,zone2,..", value_delimiter = ',')]
+    pub zone: Vec<String>,
+    /// Select these records, by id or name.
+    #[clap(long, value_name = "record1,record2,..", value_delimiter = ',')]
+    pub record: Vec<String>,
+}
+
+impl SelectOpts {
+    /// Returns whether an inventory record is selected, matching zones and
+    /// records by the ids and names of the Cloudflare record it resolved to.
+    pub fn selects(
+        &self,
+        zone: &str,
+        record: &InventoryRecord,
+        cf_record: Option<&Record>,
+    ) -> bool {
+        let group = self.group.is_empty()
+            || record.groups().iter().any(|g| self.group.contains(g));
+        let zone = self.zone.is_empty()
+            || self.zone.iter().any(|z| {
+                z == zone
+                    || cf_record
+                        .is_some_and(|r| *z == r.zone_id || *z == r.zone_name)
+            });
+        let record = self.record.is_empty()
+            || self.record.iter().any(|id_or_name| {
+                record.is(id_or_name)
+                    || cf_record.is_some_and(|r| {
+                        *id_or_name == r.id || *id_or_name == r.name
+                    })
+            });
+        group && zone && record
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReportOpts {
+    #[clap(flatten)]
+    pub select: SelectOpts,
+    /// Print a report of the records in this format, to stdout.
+    #[clap(long, value_enum, value_name = "format")]
+    pub output: Option<ReportFormat>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PlanOpts {
+    #[clap(flatten)]
+    pub select: SelectOpts,
+    /// Save the plan to a file, to make the changes with `apply`.
+    #[clap(short, long, value_name = "file")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ApplyOpts {
+    /// The plan file saved by `plan --out`.
+    #[clap(value_name = "plan")]
+    pub plan: PathBuf,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RepairOpts {
+    /// Also link records listed by id or name to their name, id and type.
+    #[clap(long)]
+    pub link: bool,
 }
 
 impl InventoryCmd {
     #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn run(self, opts: ConfigOpts) -> Result<()> {
+    pub async fn run(
+        self,
+        opts: ConfigOpts,
+        config: Option<PathBuf>,
+    ) -> Result<()> {
         // Apply CLI configuration layering
         let cli_opts = ConfigOpts::builder().inventory(Some(self.cfg)).build();
         let opts = ConfigOpts::builder().merge(opts).merge(cli_opts).build();
@@ -68,13 +225,57 @@ impl InventoryCmd {
             InventorySubcommands::Build(build_opts) => {
                 build(&opts, &build_opts).await
             }
+            InventorySubcommands::Import(import_opts) => {
+                let config = config.unwrap_or_else(default_config_path);
+                import(&opts, &import_opts, &config).await
+            }
+            InventorySubcommands::Export(export_opts) => {
+                export(&opts, &export_opts).await
+            }
             InventorySubcommands::Show(show_opts) => {
                 show(&opts, &show_opts).await
             }
-            InventorySubcommands::Check => check(&opts).await.map(|_| ()),
-            InventorySubcommands::Update => update(&opts).await,
-            InventorySubcommands::Prune => prune(&opts).await,
-            InventorySubcommands::Watch => watch(&opts).await,
+            InventorySubcommands::Lint(lint_opts) => {
+                lint(&opts, &lint_opts).await
+            }
+            InventorySubcommands::Check(report_opts) => {
+                let result = check(&opts, &report_opts.select).await?;
+                __report(&result.report(), &report_opts)
+            }
+            InventorySubcommands::Update(report_opts) => {
+                let report = update(&opts, &report_opts.select).await?;
+                __report(&report, &report_opts)
+            }
+            InventorySubcommands::Plan(plan_opts) => {
+                plan(&opts, &plan_opts).await
+            }
+            InventorySubcommands::Apply(apply_opts) => {
+                apply(&opts, &apply_opts).await
+            }
+            InventorySubcommands::Prune(report_opts) => {
+                let report = prune(&opts, &report_opts.select).await?;
+                __report(&report, &report_opts)
+            }
+            InventorySubcommands::Repair(repair_opts) => {
+                repair(&opts, &repair_opts).await
+            }
+            InventorySubcommands::Watch(select_opts) => {
+                watch(&opts, &select_opts).await
+            }
+            InventorySubcommands::Restore(restore_opts) => {
+                let inventory_path = opts
+                    .inventory
+                    .path
+                    .clone()
+                    .unwrap_or_else(default_inventory_path);
+                util::fs::restore(
+                    &inventory_path,
+                    &restore_opts,
+                    opts.inventory.backups.unwrap_or_default(),
+                    opts.inventory.dry_run.unwrap_or_default(),
+                )
+                .await
+            }
         }
     }
 }
@@ -98,7 +299,23 @@ pub async fn build(opts: &ConfigOpts, cli_opts: &BuildOpts) -> Result<()> {
     all_records.sort_by_key(|r| r.name.to_owned());
 
     let mut data = InventoryData(None);
-    if all_records.is_empty() {
+    if cli_opts.tui {
+        let statuses = statuses(&all_records).await;
+        let tui = InventoryTui::new(
+            "cddns inventory build",
+            &all_zones,
+            &all_records,
+            &data,
+            &statuses,
+        );
+        match tui.run()? {
+            TuiOutcome::Save(selected) => data = selected,
+            TuiOutcome::Quit => {
+                info!("quit without saving");
+                return Ok(());
+            }
+        }
+    } else if all_records.is_empty() {
         warn!("there are no records visible to this token, but you may save an empty inventory");
     } else {
         // Capture user input to build inventory map
@@ -184,76 +401,239 @@ pub async fn build(opts: &ConfigOpts, cli_opts: &BuildOpts) -> Result<()> {
         }
     }
 
-    if cli_opts.stdout {
-        // Print to stdout
-        println!(
-            "{}",
-            data.to_string(opts, !cli_opts.clean, !cli_opts.clean)
-                .await?
-        );
-    } else {
-        // Save file
-        let path = prompt_t::<PathBuf>(
-            format!(
-                "Save location [default: {}]",
-                default_inventory_path().display()
-            ),
-            "path",
-        )?
-        .map(|p| match p.extension() {
-            Some(_) => p,
-            None => p.with_extension("yaml"),
-        })
-        .unwrap_or_else(default_inventory_path);
-        util::fs::remove_interactive(&path).await?;
+    __save(opts, data, cli_opts.stdout, cli_opts.clean).await
+}
 
-        info!("saving inventory file...");
-        Inventory::builder()
-            .path(path)
-            .with_data(data)
-            .build()?
-            .save(opts, !cli_opts.clean, !cli_opts.clean)
-            .await?;
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn import(
+    opts: &ConfigOpts,
+    cli_opts: &ImportOpts,
+    config: &Path,
+) -> Result<()> {
+    info!("importing, please wait...");
+    let text = tokio::fs::read_to_string(&cli_opts.file)
+        .await
+        .with_context(|| format!("reading '{}'", cli_opts.file.display()))?;
+    let import = cli_opts.from.parse(&text).with_context(|| {
+        format!(
+            "reading '{}' as a {} config",
+            cli_opts.file.display(),
+            cli_opts.from
+        )
+    })?;
+    debug!(hosts = import.hosts.len(), tokens = import.tokens.len());
+
+    // Resolve hosts, preferring the configured token
+    let token = opts
+        .verify.token.as_deref()
+        .or(import.token())
+        .context("no token was provided or found, need help? see https://github.com/simbleau/cddns#readme")?;
+    trace!("retrieving cloudflare resources...");
+    let zones = cloudflare::endpoints::zones(token).await?;
+    let records = cloudflare::endpoints::records(&zones, token).await?;
+    let (data, unresolved) = import.resolve(&zones, &records);
+    for host in unresolved.iter() {
+        warn!(host, "no matching record found on cloudflare, skipping");
+    }
+    info!(
+        imported = import.hosts.len() - unresolved.len(),
+        unresolved = unresolved.len(),
+        "summary"
+    );
+
+    // Save a new token to the config
+    if let Some(token) = import.token() {
+        if opts.verify.token.as_deref() != Some(token) {
+            let save = prompt_yes_or_no(
+                format!("Save the imported token to '{}'?", config.display()),
+                "Y/n",
+            )?
+            .unwrap_or(true);
+            if save {
+                __save_token(opts, config, token).await?;
+            }
+        }
+    }
+
+    __save(opts, data, cli_opts.stdout, cli_opts.clean).await
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn export(opts: &ConfigOpts, cli_opts: &ExportOpts) -> Result<()> {
+    info!("exporting, please wait...");
+    let inventory = InventorySet::from_opts(opts).await?.data();
+
+    trace!("retrieving cloudflare resources...");
+    let token = opts
+        .verify.token.as_ref()
+        .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
+    let zones = cloudflare::endpoints::zones(token).await?;
+    let records = cloudflare::endpoints::records(&zones, token).await?;
+
+    // Resolve managed records
+    let mut managed: Vec<Record> = vec![];
+    for (inv_zone, inv_record, cf_record) in resolve(&inventory, &records)? {
+        match cf_record {
+            Some(cf_record) => {
+                if !managed.iter().any(|r| r.id == cf_record.id) {
+                    managed.push(cf_record.clone());
+                }
+            }
+            None => warn!(
+                zone = inv_zone,
+                record = inv_record,
+                "no matching record found on cloudflare, skipping"
+            ),
+        }
     }
+    debug!(
+        records = managed.len(),
+        "exporting as {:?}", cli_opts.format
+    );
 
+    print!("{}", cli_opts.format.export(&managed)?);
     Ok(())
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
 pub async fn show(opts: &ConfigOpts, cli_opts: &ShowOpts) -> Result<()> {
     info!("retrieving, please wait...");
-    let inventory_path = opts
-        .inventory
-        .path
-        .clone()
-        .unwrap_or_else(default_inventory_path);
-    let inventory = Inventory::from_file(inventory_path).await?;
-
-    if inventory.data.is_empty() {
+    let mut inventory = InventorySet::from_opts(opts).await?;
+
+    if cli_opts.tui {
+        let token = opts
+            .verify.token.as_ref()
+            .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
+        let zones = cloudflare::endpoints::zones(token).await?;
+        let records = cloudflare::endpoints::records(&zones, token).await?;
+        let statuses = statuses(&records).await;
+        let tui = InventoryTui::new(
+            format!("cddns inventory show ({})", inventory.path.display()),
+            &zones,
+            &records,
+            &inventory.data(),
+            &statuses,
+        );
+        if let TuiOutcome::Save(data) = tui.run()? {
+            info!("updating inventory file...");
+            inventory.set_data(&data);
+            inventory.save(opts, !cli_opts.clean, true).await?;
+        }
+    } else if inventory.data().is_empty() {
         warn!("inventory is empty");
     } else {
-        println!(
-            "{}",
-            inventory
-                .data
-                .to_string(opts, !cli_opts.clean, false)
-                .await?
-        );
+        // Each fragment is shown in its own format
+        let headers = inventory.fragments.len() > 1;
+        for fragment in inventory.fragments.iter() {
+            if headers {
+                println!("# ==> {} <==", fragment.path.display());
+            }
+            let contents = match (cli_opts.clean, cli_opts.expanded) {
+                (true, _) => {
+                    fragment
+                        .data
+                        .to_string(opts, fragment.format, false, false)
+                        .await?
+                }
+                (false, true) => {
+                    fragment
+                        .data
+                        .to_string(opts, fragment.format, true, false)
+                        .await?
+                }
+                (false, false) => fragment.to_string(opts, true, false).await?,
+            };
+            println!("{contents}");
+        }
+        if cli_opts.status {
+            print!("{}", __status_table(opts, &inventory.data()).await?);
+        }
+    }
+    Ok(())
+}
+
+/// Return the status of each inventory entry as a table.
+async fn __status_table(
+    opts: &ConfigOpts,
+    inventory: &InventoryData,
+) -> Result<String> {
+    let token = opts
+        .verify.token.as_ref()
+        .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
+    let zones = cloudflare::endpoints::zones(token).await?;
+    let records = cloudflare::endpoints::records(&zones, token).await?;
+    let statuses = statuses(&records).await;
+    let mut table = Table::new(&["zone", "entry", "name", "content", "status"]);
+    for (zone, entry, record) in resolve(inventory, &records)? {
+        let (name, content, status) = match record {
+            Some(record) => (
+                record.name.clone(),
+                record.content.clone(),
+                statuses.get(&record.id).copied(),
+            ),
+            None => (String::new(), String::new(), Some(RecordStatus::Invalid)),
+        };
+        let status = status.map(|s| s.to_string()).unwrap_or_default();
+        table.push(vec![zone, entry, name, content, status]);
+    }
+    Ok(table.to_aligned())
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn lint(opts: &ConfigOpts, cli_opts: &LintOpts) -> Result<()> {
+    let mut linter = Linter::new();
+    for (path, format) in InventorySet::files(opts)? {
+        debug!("linting inventory '{}'", path.display());
+        let text =
+            tokio::fs::read_to_string(&path).await.with_context(|| {
+                format!("reading inventory '{}'", path.display())
+            })?;
+        let format = InventoryFormat::resolve(format, Some(&path));
+        // Report paths relative to the working directory, when possible
+        let path = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+            .unwrap_or(path);
+        linter.add(&path, &text, format);
+    }
+    linter.lint_fragments();
+
+    if cli_opts.online {
+        info!("resolving inventory, please wait...");
+        let token = opts
+            .verify.token.as_ref()
+            .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
+        let zones = cloudflare::endpoints::zones(token).await?;
+        let records = cloudflare::endpoints::records(&zones, token).await?;
+        linter.lint_online(&zones, &records);
+    }
+
+    // Report issues
+    let issues = linter.issues();
+    for issue in issues.iter() {
+        println!("{issue}");
+    }
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    info!(errors, warnings, "summary");
+    if errors > 0 || (cli_opts.strict && warnings > 0) {
+        bail!("inventory lint failed with {errors} errors and {warnings} warnings");
     }
     Ok(())
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
-pub async fn check(opts: &ConfigOpts) -> Result<CheckResult> {
+pub async fn check(
+    opts: &ConfigOpts,
+    select: &SelectOpts,
+) -> Result<CheckResult> {
     info!("checking records, please wait...");
     // Get inventory
     trace!("refreshing inventory...");
-    let inventory_path = opts
-        .inventory
-        .path
-        .clone()
-        .unwrap_or_else(default_inventory_path);
-    let inventory = Inventory::from_file(inventory_path).await?;
+    let inventory = InventorySet::from_opts(opts).await?.data();
 
     trace!("retrieving cloudflare resources...");
     // Token is required to fix inventory record.
@@ -262,7 +642,7 @@ pub async fn check(opts: &ConfigOpts) -> Result<CheckResult> {
         .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
 
     // End early if inventory is empty
-    if inventory.data.is_empty() {
+    if inventory.is_empty() {
         warn!("inventory is empty");
         return Ok(CheckResult::default());
     }
@@ -273,81 +653,116 @@ pub async fn check(opts: &ConfigOpts) -> Result<CheckResult> {
 
     // Match zones and records
     trace!("validating records...");
-    let mut ipv4: Option<Ipv4Addr> = None;
-    let mut ipv6: Option<Ipv6Addr> = None;
-    let (mut valid, mut outdated, mut invalid) = (vec![], vec![], vec![]);
-    for (ref inv_zone, ref inv_records) in inventory.data.into_iter() {
+    let mut ips = PublicIps::default();
+    let (mut valid, mut outdated, mut invalid, mut relinked) =
+        (vec![], vec![], vec![], vec![]);
+    let mut expected = HashMap::new();
+    let mut resolved: Vec<&Record> = vec![];
+    for (ref inv_zone, ref inv_records) in inventory.records() {
         for inv_record in inv_records {
-            let cf_record = records.iter().find(|r| {
-                (r.zone_id == *inv_zone || r.zone_name == *inv_zone)
-                    && (r.id == *inv_record || r.name == *inv_record)
-            });
-            match cf_record {
-                Some(cf_record) => {
-                    let ip = match cf_record.record_type.as_str() {
-                        "A" => {
-                            match ipv4 {
-                                Some(ip) => ip,
-                                None => {
-                                    trace!("resolving ipv4...");
-                                    let ip = public_ip::addr_v4()
-                                        .await
-                                        .context("could not resolve public ipv4 needed for A record")?;
-                                    ipv4.replace(ip);
-                                    ip
-                                }
-                            }
-                        }
-                        .to_string(),
-                        "AAAA" => {
-                            match ipv6 {
-                                Some(ip) => ip,
-                                None => {
-                                    trace!("resolving ipv6...");
-                                    let ip = public_ip::addr_v6()
-                                        .await
-                                        .context("could not resolve public ipv6 needed for AAAA record")?;
-                                    ipv6.replace(ip);
-                                    ip
-                                }
-                            }
-                        }
-                        .to_string(),
-                        _ => unimplemented!(),
-                    };
-                    if cf_record.content == ip {
-                        // IP Match
-                        debug!(
-                            name = cf_record.name,
-                            id = cf_record.id,
-                            content = cf_record.content,
-                            "valid"
-                        );
-                        valid.push(cf_record.clone());
-                    } else {
-                        // IP outdated
-                        warn!(
-                            name = cf_record.name,
-                            id = cf_record.id,
-                            content = cf_record.content,
-                            "outdated"
-                        );
-                        outdated.push(cf_record.clone());
-                    }
+            let in_zone = |r: &&Record| {
+                r.zone_id == *inv_zone || r.zone_name == *inv_zone
+            };
+            // Patterns expand to the records they currently match
+            if let Some(pattern) = inv_record.pattern()? {
+                let matched = records
+                    .iter()
+                    .filter(in_zone)
+                    .filter(|r| pattern.is_match(&r.name))
+                    .filter(|r| select.selects(inv_zone, inv_record, Some(r)))
+                    .collect::<Vec<&Record>>();
+                if matched.is_empty()
+                    && select.selects(inv_zone, inv_record, None)
+                {
+                    warn!(
+                        zone = inv_zone,
+                        pattern = inv_record.key(),
+                        "pattern matches no records"
+                    );
+                }
+                resolved.extend(matched);
+                continue;
+            }
+            let mut cf_record = records
+                .iter()
+                .filter(in_zone)
+                .find(|r| inv_record.refers_to(r));
+            let relink = cf_record.is_none();
+            if relink {
+                // The record ID may have changed, re-link by name and type
+                cf_record = records
+                    .iter()
+                    .filter(in_zone)
+                    .find(|r| inv_record.relinks_to(r));
+            }
+            if !select.selects(inv_zone, inv_record, cf_record) {
+                continue;
+            }
+            if relink {
+                if let Some(cf_record) = cf_record {
+                    warn!(
+                        name = cf_record.name,
+                        id = cf_record.id,
+                        previous = inv_record.to_string(),
+                        "relinked"
+                    );
+                    relinked.push((
+                        inv_zone.clone(),
+                        inv_record.clone(),
+                        cf_record.clone(),
+                    ));
                 }
+            }
+            match cf_record {
+                Some(cf_record) => resolved.push(cf_record),
                 None => {
                     // Invalid record, no match on zone and record
-                    error!(zone = inv_zone, record = inv_record, "invalid");
+                    error!(
+                        zone = inv_zone,
+                        record = inv_record.to_string(),
+                        "invalid"
+                    );
                     invalid.push((inv_zone.clone(), inv_record.clone()));
                 }
             }
         }
     }
 
+    // Compare each record's content, once
+    let mut seen = HashSet::new();
+    for cf_record in resolved {
+        if !seen.insert(&cf_record.id) {
+            continue;
+        }
+        let ip = ips.for_record(cf_record).await?;
+        expected.insert(cf_record.id.clone(), ip.clone());
+        if cf_record.content == ip {
+            // IP Match
+            debug!(
+                name = cf_record.name,
+                id = cf_record.id,
+                content = cf_record.content,
+                "valid"
+            );
+            valid.push(cf_record.clone());
+        } else {
+            // IP outdated
+            warn!(
+                name = cf_record.name,
+                id = cf_record.id,
+                content = cf_record.content,
+                "outdated"
+            );
+            outdated.push(cf_record.clone());
+        }
+    }
+
     let result = CheckResult {
         valid,
         outdated,
         invalid,
+        relinked,
+        ips: expected,
     };
 
     // Log summary
@@ -355,6 +770,7 @@ pub async fn check(opts: &ConfigOpts) -> Result<CheckResult> {
         valid = result.valid.len(),
         outdated = result.outdated.len(),
         invalid = result.invalid.len(),
+        relinked = result.relinked.len(),
         "summary"
     );
     if !result.invalid.is_empty() {
@@ -369,6 +785,12 @@ pub async fn check(opts: &ConfigOpts) -> Result<CheckResult> {
             result.outdated.len()
         )
     }
+    if !result.relinked.is_empty() {
+        warn!(
+            "inventory contains {} re-linked records, run `cddns inventory repair` to save them",
+            result.relinked.len()
+        )
+    }
     if result.invalid.is_empty() && result.outdated.is_empty() {
         debug!("inventory contains {} valid records", result.valid.len())
     }
@@ -376,49 +798,255 @@ pub async fn check(opts: &ConfigOpts) -> Result<CheckResult> {
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
-pub async fn update(opts: &ConfigOpts) -> Result<()> {
-    let CheckResult { mut outdated, .. } = check(opts).await?;
+pub async fn update(opts: &ConfigOpts, select: &SelectOpts) -> Result<Report> {
+    let result = check(opts, select).await?;
+    let mut report = result.report();
+    let mut outdated = result.outdated;
 
     // Update outdated records
+    let mut attempted = false;
     if !outdated.is_empty() {
-        let fixed_record_ids = __update(opts, &outdated)
+        let attempts = __update(opts, &outdated)
             .await
             .context("error updating outdated records")?;
-        outdated.retain_mut(|r| !fixed_record_ids.contains(&r.id));
+        attempted = !attempts.is_empty();
+        for (cf_record, updated) in attempts {
+            match updated {
+                Ok(()) => report.updated.push(ReportRecord::new(
+                    &cf_record,
+                    result.ips.get(&cf_record.id),
+                )),
+                Err(err) => report.failed.push(ReportFailure {
+                    zone: cf_record.zone_name.clone(),
+                    record: cf_record.name.clone(),
+                    error: format!("{err:#}"),
+                }),
+            }
+        }
+        outdated.retain(|r| !report.updated.iter().any(|u| u.id == r.id));
     }
 
     // Log status
     if outdated.is_empty() {
         info!("inventory is up to date");
+    } else if !attempted {
+        info!("{} outdated records were not updated", outdated.len());
     } else {
         error!("{} outdated records remain", outdated.len());
     }
 
+    Ok(report)
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn plan(opts: &ConfigOpts, cli_opts: &PlanOpts) -> Result<()> {
+    let CheckResult { outdated, .. } = check(opts, &cli_opts.select).await?;
+
+    // Plan to point outdated records to the public IP
+    let mut ips = PublicIps::default();
+    let mut changes = vec![];
+    for cf_record in outdated.iter() {
+        let ip = ips.for_record(cf_record).await?;
+        changes.push(PlannedChange::update(cf_record, ip));
+    }
+    let plan = Plan::new(changes);
+    println!("{}", plan.diff(color_stdout()));
+
+    if let Some(path) = cli_opts.out.as_ref() {
+        plan.save(path).await?;
+        info!(
+            "plan saved, run `cddns inventory apply {}` to make these changes",
+            path.display()
+        );
+    }
     Ok(())
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
-pub async fn prune(opts: &ConfigOpts) -> Result<()> {
-    let CheckResult { mut invalid, .. } = check(opts).await?;
+pub async fn apply(opts: &ConfigOpts, cli_opts: &ApplyOpts) -> Result<()> {
+    let plan = Plan::from_file(&cli_opts.plan).await?;
+    if plan.changes.is_empty() {
+        info!("plan contains no changes");
+        return Ok(());
+    }
+    println!("{}", plan.diff(color_stdout()));
+
+    // Refuse the plan if any record changed since it was made
+    let token = opts
+        .verify.token.as_ref()
+        .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
+    let zones = cloudflare::endpoints::zones(token).await?;
+    let records = cloudflare::endpoints::records(&zones, token).await?;
+    let drifted = plan.drifted(&records);
+    for (change, drift) in drifted.iter() {
+        error!(name = change.name, id = change.id, "{drift}");
+    }
+    if !drifted.is_empty() {
+        let drifted = drifted.len();
+        bail!("{drifted} records changed since the plan was made on {}, run `cddns inventory plan` again", plan.created);
+    }
+
+    info!("applying {} changes...", plan.changes.len());
+    let (mut failed, mut skipped) = (0, 0);
+    for change in plan.changes.iter() {
+        let patched = cloudflare::endpoints::patch_record(
+            token,
+            &change.zone_id,
+            &change.id,
+            &change.patch(),
+            opts.inventory.dry_run.unwrap_or_default(),
+        )
+        .await;
+        match patched {
+            Ok(Some(_)) => {
+                info!(id = change.id, name = change.name, "applied change")
+            }
+            Ok(None) => skipped += 1,
+            Err(err) => {
+                debug!("{err:?}");
+                error!(
+                    id = change.id,
+                    name = change.name,
+                    "unsuccessful change"
+                );
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} changes failed", plan.changes.len());
+    }
+    if skipped > 0 {
+        info!("{skipped} changes were not applied");
+    } else {
+        info!("plan applied");
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn prune(opts: &ConfigOpts, select: &SelectOpts) -> Result<Report> {
+    let result = check(opts, select).await?;
+    let mut report = result.report();
+    let mut invalid = result.invalid;
 
     // Prune invalid records
+    let mut attempted = false;
     if !invalid.is_empty() {
-        let new_inventory = __prune(opts, &invalid).await?;
-        invalid.retain(|(z, r)| new_inventory.data.contains(z, r));
+        if let Some(new_inventory) = __prune(opts, &invalid).await? {
+            attempted = true;
+            let remaining = new_inventory.data();
+            for (zone, record) in invalid.iter() {
+                if remaining.contains_record(zone, record) {
+                    report.failed.push(ReportFailure {
+                        zone: zone.clone(),
+                        record: record.to_string(),
+                        error: "failed to prune record".to_string(),
+                    });
+                } else {
+                    report.pruned.push(ReportEntry {
+                        zone: zone.clone(),
+                        record: record.to_string(),
+                    });
+                }
+            }
+            invalid.retain(|(z, r)| remaining.contains_record(z, r));
+        }
     }
 
     // Log status
     if invalid.is_empty() {
         info!("inventory contains no invalid records");
+    } else if !attempted {
+        info!("{} invalid records were not pruned", invalid.len());
     } else {
         error!("{} invalid records remain", invalid.len());
     }
 
+    Ok(report)
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn repair(opts: &ConfigOpts, cli_opts: &RepairOpts) -> Result<()> {
+    let CheckResult { relinked, .. } =
+        check(opts, &SelectOpts::default()).await?;
+
+    // Records whose last-known id changed
+    let mut repairs = relinked
+        .into_iter()
+        .map(|(zone, old, record)| {
+            let linked = old.link(&record);
+            (zone, old, linked)
+        })
+        .collect::<Vec<_>>();
+
+    // Records which are not linked yet
+    if cli_opts.link {
+        let token = opts
+            .verify.token.as_ref()
+            .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
+        let zones = cloudflare::endpoints::zones(token).await?;
+        let records = cloudflare::endpoints::records(&zones, token).await?;
+        let inventory = InventorySet::from_opts(opts).await?.data();
+        for (zone, inv_records) in inventory.records() {
+            for inv_record in inv_records {
+                if inv_record.id().is_some() || inv_record.is_pattern() {
+                    continue;
+                }
+                if let Some(record) = records.iter().find(|r| {
+                    (r.zone_id == zone || r.zone_name == zone)
+                        && inv_record.refers_to(r)
+                }) {
+                    let linked = inv_record.link(record);
+                    repairs.push((zone.clone(), inv_record, linked));
+                }
+            }
+        }
+    }
+
+    if repairs.is_empty() {
+        info!("inventory needs no repairs");
+        return Ok(());
+    }
+
+    // Ask to repair records
+    let repair =
+        prompt_yes_or_no(format!("Repair {} records?", repairs.len()), "Y/n")?
+            .unwrap_or(true);
+    if !repair {
+        info!("inventory was not repaired");
+        return Ok(());
+    }
+
+    let mut inventory = InventorySet::from_opts(opts).await?;
+    let mut repaired = 0;
+    for (zone, old, record) in repairs.iter() {
+        if inventory.replace(zone, old, record.clone()) {
+            info!(zone, previous = ?old, record = ?record, "repaired record");
+            repaired += 1;
+        } else {
+            error!(zone, record = ?old, "failed to repair record");
+        }
+    }
+    if repaired > 0 {
+        info!("updating inventory file...");
+        inventory.save(opts, true, true).await?;
+    }
+    if repaired == repairs.len() {
+        info!(repaired, "inventory file repaired");
+    } else {
+        error!(
+            repaired,
+            remaining = repairs.len() - repaired,
+            "inventory file partially repaired"
+        );
+    }
+
     Ok(())
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
-pub async fn watch(opts: &ConfigOpts) -> Result<()> {
+pub async fn watch(opts: &ConfigOpts, select: &SelectOpts) -> Result<()> {
     // Override force update flag with true, to make `watch` non-interactive.
     let opts = ConfigOpts::builder()
         .merge(opts.to_owned())
@@ -435,7 +1063,7 @@ pub async fn watch(opts: &ConfigOpts) -> Result<()> {
 
     if interval.is_zero() {
         loop {
-            if let Err(e) = update(&opts).await {
+            if let Err(e) = update(&opts, select).await {
                 error!("{:?}", e);
             }
         }
@@ -445,7 +1073,7 @@ pub async fn watch(opts: &ConfigOpts) -> Result<()> {
         loop {
             timer.tick().await;
             trace!("awoken");
-            if let Err(e) = update(&opts).await {
+            if let Err(e) = update(&opts, select).await {
                 error!("{:?}", e);
             }
             trace!("sleeping...");
@@ -453,32 +1081,273 @@ pub async fn watch(opts: &ConfigOpts) -> Result<()> {
     }
 }
 
+/// The status of an inventory record against Cloudflare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordStatus {
+    /// The record points to the current public IP.
+    Valid,
+    /// The record points to a stale IP.
+    Outdated,
+    /// The record does not exist on Cloudflare.
+    Invalid,
+}
+
+impl Display for RecordStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordStatus::Valid => write!(f, "valid"),
+            RecordStatus::Outdated => write!(f, "outdated"),
+            RecordStatus::Invalid => write!(f, "invalid"),
+        }
+    }
+}
+
+/// Return the status of Cloudflare records by record id, on a best-effort
+/// basis. Records whose public IP can not be resolved are omitted.
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn statuses(records: &[Record]) -> HashMap<String, RecordStatus> {
+    let mut ips = PublicIps::default();
+    let mut unresolvable = HashSet::new();
+    let mut statuses = HashMap::new();
+    for record in records {
+        // Only A and AAAA records point to a public IP
+        if !matches!(record.record_type.as_str(), "A" | "AAAA")
+            || unresolvable.contains(&record.record_type)
+        {
+            continue;
+        }
+        match ips.for_record(record).await {
+            Ok(ip) => {
+                let status = if record.content == ip {
+                    RecordStatus::Valid
+                } else {
+                    RecordStatus::Outdated
+                };
+                statuses.insert(record.id.clone(), status);
+            }
+            Err(err) => {
+                warn!("{err}");
+                unresolvable.insert(record.record_type.clone());
+            }
+        }
+    }
+    statuses
+}
+
+/// Resolve inventory entries to the Cloudflare records they refer to, as
+/// `(zone, entry, record)`. Patterns resolve to each record they match, and
+/// other entries which match no record resolve to `None`.
+pub fn resolve<'a>(
+    inventory: &InventoryData,
+    records: &'a [Record],
+) -> Result<Vec<(String, String, Option<&'a Record>)>> {
+    let mut resolved = vec![];
+    for (inv_zone, inv_records) in inventory.records() {
+        for inv_record in inv_records {
+            let in_zone =
+                |r: &&Record| r.zone_id == inv_zone || r.zone_name == inv_zone;
+            if let Some(pattern) = inv_record.pattern()? {
+                for cf_record in records
+                    .iter()
+                    .filter(in_zone)
+                    .filter(|r| pattern.is_match(&r.name))
+                {
+                    resolved.push((
+                        inv_zone.clone(),
+                        inv_record.key().to_string(),
+                        Some(cf_record),
+                    ));
+                }
+                continue;
+            }
+            let cf_record = records
+                .iter()
+                .filter(in_zone)
+                .find(|r| inv_record.refers_to(r))
+                .or_else(|| {
+                    records
+                        .iter()
+                        .filter(in_zone)
+                        .find(|r| inv_record.relinks_to(r))
+                });
+            resolved.push((
+                inv_zone.clone(),
+                inv_record.key().to_string(),
+                cf_record,
+            ));
+        }
+    }
+    Ok(resolved)
+}
+
+/// Returns whether output to stdout should be colored.
+fn color_stdout() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct CheckResult {
-    valid: Vec<Record>,
-    outdated: Vec<Record>,
-    invalid: Vec<(String, String)>,
+    pub valid: Vec<Record>,
+    pub outdated: Vec<Record>,
+    pub invalid: Vec<(String, InventoryRecord)>,
+    pub relinked: Vec<(String, InventoryRecord, Record)>,
+    /// The public IP each valid or outdated record should point to, by id.
+    pub ips: HashMap<String, String>,
+}
+
+impl CheckResult {
+    /// Return a report of the checked records.
+    pub fn report(&self) -> Report {
+        Report::new(&self.valid, &self.outdated, &self.invalid, &self.ips)
+    }
+}
+
+/// Print a report in the requested format, if any, and fail with the exit
+/// code of the report.
+fn __report(report: &Report, cli_opts: &ReportOpts) -> Result<()> {
+    if let Some(format) = cli_opts.output {
+        println!("{}", format.encode(report)?.trim_end());
+    }
+    match report.exit_error() {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+/// Print inventory data to stdout, or save it to a file chosen by the user.
+#[tracing::instrument(level = "trace", skip_all)]
+async fn __save(
+    opts: &ConfigOpts,
+    data: InventoryData,
+    stdout: bool,
+    clean: bool,
+) -> Result<()> {
+    if stdout {
+        // Print to stdout
+        println!(
+            "{}",
+            data.to_string(
+                opts,
+                opts.inventory.format.unwrap_or_default(),
+                !clean,
+                !clean
+            )
+            .await?
+        );
+    } else {
+        // Save file, with an extension matching the format
+        let format = opts.inventory.format;
+        let default_path = match format {
+            Some(format) => {
+                default_inventory_path().with_extension(format.extension())
+            }
+            None => default_inventory_path(),
+        };
+        let path = prompt_t::<PathBuf>(
+            format!("Save location [default: {}]", default_path.display()),
+            "path",
+        )?
+        .map(|p| match p.extension() {
+            Some(_) => p,
+            None => p.with_extension(format.unwrap_or_default().extension()),
+        })
+        .unwrap_or(default_path);
+        util::fs::overwrite_interactive(&path).await?;
+
+        info!("saving inventory file...");
+        Inventory::builder()
+            .path(path)
+            .format(format)
+            .with_data(data)
+            .build()?
+            .save(opts, !clean, !clean)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Save an API token to the config file, keeping its comments. The token is
+/// written to a file beside the config and referenced by `verify.token_file`,
+/// unless the user prefers to store it in the config itself.
+async fn __save_token(
+    opts: &ConfigOpts,
+    config: &Path,
+    token: &str,
+) -> Result<()> {
+    let backups = opts.inventory.backups.unwrap_or_default();
+    let dry_run = opts.inventory.dry_run.unwrap_or_default();
+    let token_file = std::path::absolute(config.with_file_name("token"))
+        .context("resolving token file path")?;
+    let use_file = prompt_yes_or_no(
+        format!(
+            "Save the token in '{}' rather than in plaintext in the config?",
+            token_file.display()
+        ),
+        "Y/n",
+    )?
+    .unwrap_or(true);
+
+    let text = match config.exists() {
+        true => {
+            std::fs::read_to_string(config).context("reading config file")?
+        }
+        false => String::new(),
+    };
+    let mut editor = ConfigEditor::parse(&text)?;
+    if use_file {
+        #[cfg_attr(not(unix), allow(unused_variables))]
+        let written =
+            util::fs::save(&token_file, token, backups, dry_run).await?;
+        #[cfg(unix)]
+        if written {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = std::fs::Permissions::from_mode(0o600);
+            tokio::fs::set_permissions(&token_file, permissions)
+                .await
+                .with_context(|| {
+                    format!("restricting '{}'", token_file.display())
+                })?;
+        }
+        // An inline token would take precedence over the file
+        editor.unset(None, "verify.token")?;
+        editor.set(None, "verify.token_file", &token_file.to_string_lossy())?;
+    } else {
+        editor.set(None, "verify.token", token)?;
+    }
+    util::fs::save(config, editor.to_string(), backups, dry_run).await?;
+    match use_file {
+        true => info!(
+            "saved token to '{}', referenced by '{}'",
+            token_file.display(),
+            config.display()
+        ),
+        false => info!("saved token to '{}'", config.display()),
+    }
+    Ok(())
 }
 
-/// Update a list of outdated records, returning those ids which were
-/// successfully updated.
+/// Update a list of outdated records, returning the records an update was
+/// attempted for, with its outcome.
 #[tracing::instrument(level = "trace", skip_all)]
 async fn __update(
     opts: &ConfigOpts,
-    outdated: &Vec<Record>,
-) -> Result<HashSet<String>> {
-    // Track fixed records
-    let mut updated_ids = HashSet::new();
+    outdated: &[Record],
+) -> Result<Vec<(Record, Result<()>)>> {
+    // Track attempted updates
+    let mut attempts = vec![];
     // Fix outdated records
     if !outdated.is_empty() {
         let force = opts
             .inventory
             .force_update
             .context("no default force option")?;
-        debug!(force_update = force);
+        let dry_run = opts.inventory.dry_run.unwrap_or_default();
+        debug!(force_update = force, dry_run);
 
-        // Ask to fix records
-        let fix = force || {
+        // Ask to fix records, unless only logging the updates
+        let fix = force || dry_run || {
             prompt_yes_or_no(
                 format!("Update {} outdated records?", outdated.len()),
                 "Y/n",
@@ -490,76 +1359,52 @@ async fn __update(
             let token = opts
                 .verify.token.as_ref()
                 .context("no token was provided, need help? see https://github.com/simbleau/cddns#readme")?;
-            let mut ipv4: Option<Ipv4Addr> = None;
-            let mut ipv6: Option<Ipv6Addr> = None;
+            let mut ips = PublicIps::default();
             for cf_record in outdated.iter() {
-                let updated = match cf_record.record_type.as_str() {
-                    "A" => {
-                        update_record(
-                            &token,
-                            &cf_record.zone_id,
-                            &cf_record.id,
-                            ipv4.get_or_insert({
-                                trace!("resolving ipv4...");
-                                public_ip::addr_v4()
-                                    .await
-                                    .context("could not resolve ipv4 address")?
-                            })
-                            .to_string(),
-                        )
-                        .await
+                let ip = ips.for_record(cf_record).await?;
+                let updated = update_record(
+                    &token,
+                    &cf_record.zone_id,
+                    &cf_record.id,
+                    ip,
+                    dry_run,
+                )
+                .await;
+                match updated {
+                    Ok(true) => {
+                        info!(
+                            id = cf_record.id,
+                            name = cf_record.name,
+                            "updated record"
+                        );
+                        attempts.push((cf_record.clone(), Ok(())));
                     }
-                    "AAAA" => {
-                        update_record(
-                            &token,
-                            &cf_record.zone_id,
-                            &cf_record.id,
-                            ipv6.get_or_insert({
-                                trace!("resolving ipv6...");
-                                public_ip::addr_v6()
-                                    .await
-                                    .context("could not resolve ipv6 address")?
-                            })
-                            .to_string(),
-                        )
-                        .await
+                    Ok(false) => {}
+                    Err(err) => {
+                        debug!("{err:?}");
+                        error!(
+                            id = cf_record.id,
+                            name = cf_record.name,
+                            "unsuccessful record update"
+                        );
+                        attempts.push((cf_record.clone(), Err(err)));
                     }
-                    _ => unimplemented!(),
-                };
-                if let Err(err) = updated {
-                    debug!("{err:?}");
-                    error!(
-                        id = cf_record.id,
-                        name = cf_record.name,
-                        "unsuccessful record update"
-                    );
-                } else {
-                    info!(
-                        id = cf_record.id,
-                        name = cf_record.name,
-                        "updated record"
-                    );
-                    updated_ids.insert(cf_record.id.clone());
                 }
             }
         }
     }
-    Ok(updated_ids)
+    Ok(attempts)
 }
 
-/// Prune invalid records, returning the resulting inventory.
+/// Prune invalid records, returning the resulting inventory, or nothing if
+/// pruning was declined or the inventory was not written.
 #[tracing::instrument(level = "trace", skip_all)]
 async fn __prune(
     opts: &ConfigOpts,
-    invalid: &Vec<(String, String)>,
-) -> Result<Inventory> {
+    invalid: &[(String, InventoryRecord)],
+) -> Result<Option<InventorySet>> {
     // Get inventory
-    let inventory_path = opts
-        .inventory
-        .path
-        .clone()
-        .unwrap_or_else(default_inventory_path);
-    let mut inventory = Inventory::from_file(inventory_path).await?;
+    let mut inventory = InventorySet::from_opts(opts).await?;
 
     // Prune invalid records
     if !invalid.is_empty() {
@@ -567,10 +1412,11 @@ async fn __prune(
             .inventory
             .force_prune
             .context("no default force option")?;
-        debug!(force_prune = force);
+        let dry_run = opts.inventory.dry_run.unwrap_or_default();
+        debug!(force_prune = force, dry_run);
 
-        // Ask to prune records
-        let prune = force || {
+        // Ask to prune records, unless only logging the changes
+        let prune = force || dry_run || {
             prompt_yes_or_no(
                 format!("Prune {} invalid records?", invalid.len()),
                 "Y/n",
@@ -579,24 +1425,32 @@ async fn __prune(
         };
         // Prune
         if prune {
-            let mut pruned = 0;
+            let mut pruned = vec![];
             info!("pruning {} invalid records...", invalid.len());
-            for (zone_id, record_id) in invalid.iter() {
-                let removed = inventory.data.remove(zone_id, record_id);
-                if let Ok(true) = removed {
-                    info!(zone = zone_id, record = record_id, "pruned record");
-                    pruned += 1;
+            for (zone_id, record) in invalid.iter() {
+                if inventory.remove_record(zone_id, record) {
+                    pruned.push((zone_id, record));
                 } else {
                     error!(
                         zone = zone_id,
-                        record = record_id,
+                        record = record.to_string(),
                         "failed to prune record"
                     );
                 }
             }
-            if pruned > 0 {
+            if !pruned.is_empty() {
                 info!("updating inventory file...");
-                inventory.save(opts, true, true).await?;
+                if !inventory.save(opts, true, true).await? {
+                    return Ok(None);
+                }
+                for (zone_id, record) in pruned.iter() {
+                    info!(
+                        zone = zone_id,
+                        record = record.to_string(),
+                        "pruned record"
+                    );
+                }
+                let pruned = pruned.len();
                 if invalid.len() == pruned {
                     info!(
                         pruned,
@@ -610,8 +1464,9 @@ async fn __prune(
                     );
                 }
             }
+            return Ok(Some(inventory));
         }
     }
 
-    Ok(inventory)
+    Ok(None)
 }
@@ -1,9 +1,20 @@
 use crate::cloudflare;
 use crate::cloudflare::models::{Record, Zone};
+use crate::cmd::inventory::{resolve, statuses, RecordStatus};
 use crate::config::models::{ConfigOpts, ConfigOptsList};
-use anyhow::{Context, Result};
+use crate::inventory::models::InventorySet;
+use crate::util::ip::Cidr;
+use crate::util::pattern::Pattern;
+use crate::util::stdout;
+use crate::util::table::Table;
+use anyhow::{bail, Context, Result};
 use clap::{Args, Subcommand};
 use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::{self, Display, Write};
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
 use tracing::{debug, info, trace};
 
 /// List available resources
@@ -14,6 +25,8 @@ pub struct ListCmd {
     action: Option<ListSubcommands>,
     #[clap(flatten)]
     pub cfg: ConfigOptsList,
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 #[derive(Clone, Debug, Subcommand)]
@@ -24,6 +37,87 @@ enum ListSubcommands {
     Records(RecordOpts),
 }
 
+/// Options for how resources are printed.
+#[derive(Debug, Clone, Args)]
+pub struct OutputOpts {
+    /// Print resources in this format. [default: `name: id` lines]
+    #[clap(long, value_enum, value_name = "format", global = true)]
+    pub output: Option<ListFormat>,
+    /// Print a line per resource from a template, e.g. '{name} {content}'.
+    #[clap(
+        long,
+        value_name = "template",
+        global = true,
+        conflicts_with = "output"
+    )]
+    pub template: Option<String>,
+    /// Mark each record as valid or outdated against the public IP, and
+    /// whether the inventory manages it.
+    #[clap(long, global = true)]
+    pub status: bool,
+}
+
+/// The formats resources can be printed as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListFormat {
+    /// Aligned columns with a header.
+    Table,
+    Json,
+    Yaml,
+    /// Comma-separated values with a header.
+    Csv,
+}
+
+/// The zones and records printed by `list`.
+#[derive(Serialize)]
+struct Resources<'a> {
+    zones: &'a [Zone],
+    records: &'a [ListedRecord<'a>],
+}
+
+/// A record, with its status when requested.
+#[derive(Serialize)]
+struct ListedRecord<'a> {
+    #[serde(flatten)]
+    record: &'a Record,
+    #[serde(flatten)]
+    status: Option<Status>,
+}
+
+impl Display for ListedRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.record)?;
+        match self.status {
+            Some(status) => write!(f, "{status}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The status of a record against the public IP and the inventory.
+#[derive(Clone, Copy, Serialize)]
+struct Status {
+    /// Unknown for records which do not point to a public IP.
+    status: Option<RecordStatus>,
+    managed: bool,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut marks = vec![];
+        if let Some(status) = self.status {
+            marks.push(status.to_string());
+        }
+        if self.managed {
+            marks.push("managed".to_string());
+        }
+        match marks.is_empty() {
+            true => Ok(()),
+            false => write!(f, " [{}]", marks.join(", ")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct ZoneOpts {
     /// Print a single zone matching a name or id.
@@ -53,20 +147,20 @@ impl ListCmd {
         match self.action {
             Some(subcommand) => match subcommand {
                 ListSubcommands::Zones(cli_zone_opts) => {
-                    list_zones(&opts, &cli_zone_opts).await
+                    list_zones(&opts, &cli_zone_opts, &self.output).await
                 }
                 ListSubcommands::Records(cli_record_opts) => {
-                    list_records(&opts, &cli_record_opts).await
+                    list_records(&opts, &cli_record_opts, &self.output).await
                 }
             },
-            None => list_all(&opts).await,
+            None => list_all(&opts, &self.output).await,
         }
     }
 }
 
 /// Print all zones and records.
 #[tracing::instrument(level = "trace", skip_all)]
-async fn list_all(opts: &ConfigOpts) -> Result<()> {
+async fn list_all(opts: &ConfigOpts, output: &OutputOpts) -> Result<()> {
     // Get token
     let token = opts
         .verify.token.as_ref()
@@ -77,7 +171,8 @@ async fn list_all(opts: &ConfigOpts) -> Result<()> {
     let mut zones = cloudflare::endpoints::zones(&token).await?;
     retain_zones(&mut zones, opts)?;
     // Get records
-    let mut records = cloudflare::endpoints::records(&zones, &token).await?;
+    let mut records =
+        cloudflare::endpoints::all_records(&zones, &token).await?;
     retain_records(&mut records, opts)?;
     debug!(
         "received {} zones with {} records",
@@ -85,19 +180,38 @@ async fn list_all(opts: &ConfigOpts) -> Result<()> {
         records.len()
     );
 
-    // Print all
-    for zone in zones.iter() {
-        println!("{zone}");
-        for record in records.iter().filter(|r| r.zone_id == zone.id) {
-            println!("  - {record}");
+    let records = listed(opts, output, &records).await?;
+
+    // Print all, where tables hold the records with their zone
+    if output.output == Some(ListFormat::Table) {
+        stdout::print(format!("{}\n", zone_table(&zones).to_aligned()))?;
+    }
+    let resources = Resources {
+        zones: &zones,
+        records: &records,
+    };
+    if !print_formatted(output, &resources, record_table(&records))? {
+        let mut printed = String::new();
+        for zone in zones.iter() {
+            let _ = writeln!(printed, "{zone}");
+            for listed in records.iter() {
+                if listed.record.zone_id == zone.id {
+                    let _ = writeln!(printed, "  - {listed}");
+                }
+            }
         }
+        stdout::print(printed)?;
     }
     Ok(())
 }
 
 /// Print only zones.
 #[tracing::instrument(level = "trace", skip_all)]
-async fn list_zones(opts: &ConfigOpts, cli_opts: &ZoneOpts) -> Result<()> {
+async fn list_zones(
+    opts: &ConfigOpts,
+    cli_opts: &ZoneOpts,
+    output: &OutputOpts,
+) -> Result<()> {
     // Get token
     let token = opts
         .verify.token.as_ref()
@@ -115,15 +229,23 @@ async fn list_zones(opts: &ConfigOpts, cli_opts: &ZoneOpts) -> Result<()> {
     }
 
     // Print zones
-    for zone in zones {
-        println!("{zone}");
+    if !print_formatted(output, &zones, zone_table(&zones))? {
+        let mut printed = String::new();
+        for zone in zones {
+            let _ = writeln!(printed, "{zone}");
+        }
+        stdout::print(printed)?;
     }
     Ok(())
 }
 
 /// Print only records.
 #[tracing::instrument(level = "trace", skip_all)]
-async fn list_records(opts: &ConfigOpts, cli_opts: &RecordOpts) -> Result<()> {
+async fn list_records(
+    opts: &ConfigOpts,
+    cli_opts: &RecordOpts,
+    output: &OutputOpts,
+) -> Result<()> {
     // Get token
     let token = opts
         .verify.token.as_ref()
@@ -140,7 +262,8 @@ async fn list_records(opts: &ConfigOpts, cli_opts: &RecordOpts) -> Result<()> {
     }
 
     // Get records
-    let mut records = cloudflare::endpoints::records(&zones, &token).await?;
+    let mut records =
+        cloudflare::endpoints::all_records(&zones, &token).await?;
     // Apply filtering
     if let Some(ref record_id) = cli_opts.record {
         records = vec![find_record(&records, record_id)
@@ -149,16 +272,138 @@ async fn list_records(opts: &ConfigOpts, cli_opts: &RecordOpts) -> Result<()> {
         retain_records(&mut records, opts)?;
     }
 
+    let records = listed(opts, output, &records).await?;
+
     // Print records
-    for record in records {
-        println!("{record}");
+    if !print_formatted(output, &records, record_table(&records))? {
+        let mut printed = String::new();
+        for listed in records {
+            let _ = writeln!(printed, "{listed}");
+        }
+        stdout::print(printed)?;
     }
     Ok(())
 }
 
+/// Print resources in the requested format or template. Returns whether a
+/// format or template was requested.
+fn print_formatted(
+    output: &OutputOpts,
+    resources: &impl Serialize,
+    table: Table,
+) -> Result<bool> {
+    let printed = match (output.output, output.template.as_ref()) {
+        (_, Some(template)) => table.to_template(template)?,
+        (Some(ListFormat::Table), _) => table.to_aligned(),
+        (Some(ListFormat::Json), _) => {
+            crate::util::encoding::as_json(resources)? + "\n"
+        }
+        (Some(ListFormat::Yaml), _) => {
+            crate::util::encoding::as_yaml(resources)?
+        }
+        (Some(ListFormat::Csv), _) => table.to_csv(),
+        (None, None) => return Ok(false),
+    };
+    stdout::print(printed)?;
+    Ok(true)
+}
+
+/// Return zones as a table, with a column per field.
+fn zone_table(zones: &[Zone]) -> Table {
+    let mut table = Table::new(&["id", "name", "status", "permissions"]);
+    for zone in zones {
+        table.push(vec![
+            zone.id.clone(),
+            zone.name.clone(),
+            zone.status.clone(),
+            zone.permissions.join(","),
+        ]);
+    }
+    table
+}
+
+/// Return records as a table, with a column per field.
+fn record_table(records: &[ListedRecord]) -> Table {
+    let mut columns = vec![
+        "zone_id",
+        "zone_name",
+        "id",
+        "name",
+        "type",
+        "content",
+        "ttl",
+        "proxied",
+        "locked",
+    ];
+    let with_status = records.iter().any(|r| r.status.is_some());
+    if with_status {
+        columns.extend(["status", "managed"]);
+    }
+    let mut table = Table::new(&columns);
+    for ListedRecord { record, status } in records {
+        let mut row = vec![
+            record.zone_id.clone(),
+            record.zone_name.clone(),
+            record.id.clone(),
+            record.name.clone(),
+            record.record_type.clone(),
+            record.content.clone(),
+            record.ttl.to_string(),
+            record.proxied.to_string(),
+            record.locked.to_string(),
+        ];
+        if with_status {
+            let state = status.and_then(|s| s.status);
+            row.push(state.map(|s| s.to_string()).unwrap_or_default());
+            row.push(status.is_some_and(|s| s.managed).to_string());
+        }
+        table.push(row);
+    }
+    table
+}
+
+/// Return records for printing, with their status if requested.
+async fn listed<'a>(
+    opts: &ConfigOpts,
+    output: &OutputOpts,
+    records: &'a [Record],
+) -> Result<Vec<ListedRecord<'a>>> {
+    if !output.status {
+        return Ok(records
+            .iter()
+            .map(|record| ListedRecord {
+                record,
+                status: None,
+            })
+            .collect());
+    }
+    trace!("resolving record statuses...");
+    let statuses = statuses(records).await;
+    let managed = match InventorySet::from_opts(opts).await {
+        Ok(inventory) => resolve(&inventory.data(), records)?
+            .into_iter()
+            .filter_map(|(_, _, record)| record.map(|r| r.id.clone()))
+            .collect::<HashSet<String>>(),
+        Err(err) => {
+            debug!("no inventory to mark managed records: {err:#}");
+            HashSet::new()
+        }
+    };
+    Ok(records
+        .iter()
+        .map(|record| ListedRecord {
+            record,
+            status: Some(Status {
+                status: statuses.get(&record.id).copied(),
+                managed: managed.contains(&record.id),
+            }),
+        })
+        .collect())
+}
+
 /// Find a zone matching the given identifier.
 #[tracing::instrument(level = "trace", skip_all)]
-pub fn find_zone(zones: &Vec<Zone>, id: impl Into<String>) -> Option<Zone> {
+pub fn find_zone(zones: &[Zone], id: impl Into<String>) -> Option<Zone> {
     let id_str = id.into();
     for z in zones {
         if id_str == z.id || id_str == z.name {
@@ -173,25 +418,35 @@ pub fn find_zone(zones: &Vec<Zone>, id: impl Into<String>) -> Option<Zone> {
 pub fn retain_zones(zones: &mut Vec<Zone>, opts: &ConfigOpts) -> Result<()> {
     let beginning_amt = zones.len();
     // Filter zones by configuration options
-    if let Some(include_filters) = opts.list.include_zones.as_ref() {
-        for filter_str in include_filters {
-            debug!("applying include filter: '{}'", filter_str);
-            let pattern = Regex::new(filter_str)
-                .context("compiling include_zones regex filter")?;
-            zones.retain(|z| {
-                pattern.is_match(&z.id) || pattern.is_match(&z.name)
-            });
-        }
-    }
-    if let Some(ignore_filters) = opts.list.ignore_zones.as_ref() {
-        for filter_str in ignore_filters {
-            debug!("applying ignore filter: '{}'", filter_str);
-            let pattern = Regex::new(filter_str)
-                .context("compiling ignore_zones regex filter")?;
-            zones.retain(|z| {
-                !pattern.is_match(&z.id) && !pattern.is_match(&z.name)
-            });
-        }
+    let include = patterns(opts.list.include_zones.as_ref(), opts)
+        .context("compiling include_zones filters")?;
+    if !include.is_empty() {
+        debug!("applying {} include filters", include.len());
+        zones.retain(|z| {
+            include
+                .iter()
+                .any(|p| p.is_match(&z.id) || p.is_match(&z.name))
+        });
+    }
+    let ignore = patterns(opts.list.ignore_zones.as_ref(), opts)
+        .context("compiling ignore_zones filters")?;
+    if !ignore.is_empty() {
+        debug!("applying {} ignore filters", ignore.len());
+        zones.retain(|z| {
+            !ignore
+                .iter()
+                .any(|p| p.is_match(&z.id) || p.is_match(&z.name))
+        });
+    }
+    if let Some(statuses) =
+        opts.list.zone_status.as_ref().filter(|s| !s.is_empty())
+    {
+        debug!("applying zone status filter: {}", statuses.join(", "));
+        zones.retain(|z| {
+            statuses
+                .iter()
+                .any(|s| s.trim().eq_ignore_ascii_case(&z.status))
+        });
     }
     debug!("filtered out {} zones", beginning_amt - zones.len());
     Ok(())
@@ -200,7 +455,7 @@ pub fn retain_zones(zones: &mut Vec<Zone>, opts: &ConfigOpts) -> Result<()> {
 /// Find a record matching the given identifier.
 #[tracing::instrument(level = "trace", skip_all)]
 pub fn find_record(
-    records: &Vec<Record>,
+    records: &[Record],
     id: impl Into<String>,
 ) -> Option<Record> {
     let id_str = id.into();
@@ -220,26 +475,202 @@ pub fn retain_records(
 ) -> Result<()> {
     let beginning_amt = records.len();
     // Filter records by configuration options
-    if let Some(include_filters) = opts.list.include_records.as_ref() {
-        for filter_str in include_filters {
-            debug!("applying include filter: '{}'", filter_str);
-            let pattern = Regex::new(filter_str)
-                .context("compiling include_records regex filter")?;
-            records.retain(|r| {
-                pattern.is_match(&r.id) || pattern.is_match(&r.name)
-            });
-        }
-    }
-    if let Some(ignore_filters) = opts.list.ignore_records.as_ref() {
-        for filter_str in ignore_filters {
-            debug!("applying ignore filter: '{}'", filter_str);
-            let pattern = Regex::new(filter_str)
-                .context("compiling ignore_records regex filter")?;
-            records.retain(|r| {
-                !pattern.is_match(&r.id) && !pattern.is_match(&r.name)
-            });
-        }
+    let include = patterns(opts.list.include_records.as_ref(), opts)
+        .context("compiling include_records filters")?;
+    if !include.is_empty() {
+        debug!("applying {} include filters", include.len());
+        records.retain(|r| {
+            include
+                .iter()
+                .any(|p| p.is_match(&r.id) || p.is_match(&r.name))
+        });
+    }
+    let ignore = patterns(opts.list.ignore_records.as_ref(), opts)
+        .context("compiling ignore_records filters")?;
+    if !ignore.is_empty() {
+        debug!("applying {} ignore filters", ignore.len());
+        records.retain(|r| {
+            !ignore
+                .iter()
+                .any(|p| p.is_match(&r.id) || p.is_match(&r.name))
+        });
+    }
+    if let Some(types) = opts.list.types.as_ref().filter(|t| !t.is_empty()) {
+        debug!("applying type filter: {}", types.join(", "));
+        records.retain(|r| {
+            types
+                .iter()
+                .any(|t| t.trim().eq_ignore_ascii_case(&r.record_type))
+        });
+    }
+    if let Some(content_filters) =
+        opts.list.content.as_ref().filter(|c| !c.is_empty())
+    {
+        let filters = content_filters
+            .iter()
+            .map(|filter_str| ContentFilter::new(filter_str))
+            .collect::<Result<Vec<_>>>()?;
+        debug!("applying content filters: {}", content_filters.join(", "));
+        records.retain(|r| filters.iter().any(|f| f.is_match(&r.content)));
+    }
+    if let Some(proxied) = opts.list.proxied {
+        debug!("applying proxied filter: {}", proxied);
+        records.retain(|r| r.proxied == proxied);
+    }
+    if let Some(ttl) = opts.list.ttl.as_ref() {
+        debug!("applying ttl filter: '{}'", ttl);
+        let range = parse_ttl(ttl)?;
+        records.retain(|r| range.contains(&r.ttl));
     }
     debug!("filtered out {} records", beginning_amt - records.len());
     Ok(())
 }
+
+/// Return every problem with the configured filters, compiling each one.
+pub fn check_filters(opts: &ConfigOpts) -> Vec<String> {
+    let syntax = opts.list.pattern_syntax.unwrap_or_default();
+    let anchored = opts.list.anchored.unwrap_or_default();
+    let mut problems = vec![];
+    for (name, filters) in [
+        ("include_zones", &opts.list.include_zones),
+        ("ignore_zones", &opts.list.ignore_zones),
+        ("include_records", &opts.list.include_records),
+        ("ignore_records", &opts.list.ignore_records),
+    ] {
+        for filter in filters.iter().flatten() {
+            if let Err(err) = Pattern::new(filter, syntax, anchored) {
+                problems.push(format!("`list.{name}`: {err:#}"));
+            }
+        }
+    }
+    for filter in opts.list.content.iter().flatten() {
+        if let Err(err) = ContentFilter::new(filter) {
+            problems.push(format!("`list.content`: '{filter}': {err:#}"));
+        }
+    }
+    if let Some(Err(err)) = opts.list.ttl.as_deref().map(parse_ttl) {
+        problems.push(format!("`list.ttl`: {err:#}"));
+    }
+    problems
+}
+
+/// Compile zone or record filters with the configured pattern syntax.
+fn patterns(
+    filters: Option<&Vec<String>>,
+    opts: &ConfigOpts,
+) -> Result<Vec<Pattern>> {
+    let syntax = opts.list.pattern_syntax.unwrap_or_default();
+    let anchored = opts.list.anchored.unwrap_or_default();
+    filters
+        .into_iter()
+        .flatten()
+        .map(|filter| Pattern::new(filter, syntax, anchored))
+        .collect()
+}
+
+/// A filter on record content, by CIDR range or regex.
+enum ContentFilter {
+    Cidr(Cidr),
+    Regex(Regex),
+}
+
+impl ContentFilter {
+    /// Parse a filter, as a CIDR range if it looks like one, else a regex.
+    fn new(filter_str: &str) -> Result<Self> {
+        match filter_str.parse::<Cidr>() {
+            Ok(cidr) => Ok(Self::Cidr(cidr)),
+            Err(_) => Regex::new(filter_str)
+                .map(Self::Regex)
+                .context("compiling content regex filter"),
+        }
+    }
+
+    /// Returns whether the content of a record matches the filter.
+    fn is_match(&self, content: &str) -> bool {
+        match self {
+            Self::Cidr(cidr) => {
+                content.parse::<IpAddr>().is_ok_and(|ip| cidr.contains(ip))
+            }
+            Self::Regex(pattern) => pattern.is_match(content),
+        }
+    }
+}
+
+/// Parse a TTL filter: seconds, `auto`, or a range such as `60..300`, `..300`
+/// or `3600..`. Cloudflare reports an automatic TTL as 1.
+fn parse_ttl(ttl: &str) -> Result<RangeInclusive<u32>> {
+    let seconds = |s: &str, default: u32| -> Result<u32> {
+        match s.trim() {
+            "" => Ok(default),
+            "auto" => Ok(1),
+            s => s.parse::<u32>().with_context(|| {
+                format!("invalid ttl '{ttl}', expected seconds, 'auto', or a range such as '60..300'")
+            }),
+        }
+    };
+    let range = match ttl.split_once("..") {
+        Some((min, max)) => seconds(min, 0)?..=seconds(max, u32::MAX)?,
+        None if ttl.trim().is_empty() => {
+            bail!("invalid ttl '{ttl}', expected seconds, 'auto', or a range such as '60..300'")
+        }
+        None => {
+            let ttl = seconds(ttl, 0)?;
+            ttl..=ttl
+        }
+    };
+    if range.is_empty() {
+        bail!("invalid ttl range '{ttl}', the minimum exceeds the maximum");
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ttls() -> Result<()> {
+        assert_eq!(parse_ttl("300")?, 300..=300);
+        assert_eq!(parse_ttl("auto")?, 1..=1);
+        assert_eq!(parse_ttl("60..300")?, 60..=300);
+        assert_eq!(parse_ttl("..300")?, 0..=300);
+        assert_eq!(parse_ttl("3600..")?, 3600..=u32::MAX);
+        assert_eq!(parse_ttl("auto..60")?, 1..=60);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_ttls() {
+        for ttl in ["", "300..60", "5m", "-1", "1..2..3"] {
+            assert!(parse_ttl(ttl).is_err(), "{ttl}");
+        }
+        let err = parse_ttl("300..60").map_err(|err| err.to_string());
+        assert_eq!(
+            err.err().as_deref(),
+            Some(
+                "invalid ttl range '300..60', the minimum exceeds the maximum"
+            )
+        );
+    }
+
+    #[test]
+    fn filters_content() -> Result<()> {
+        let cidr = ContentFilter::new("192.0.2.0/24")?;
+        assert!(matches!(cidr, ContentFilter::Cidr(_)));
+        assert!(cidr.is_match("192.0.2.10"));
+        assert!(!cidr.is_match("198.51.100.1"));
+        assert!(!cidr.is_match("v=spf1 ip4:192.0.2.0/24 -all"));
+
+        let v6 = ContentFilter::new("2001:db8::/32")?;
+        assert!(v6.is_match("2001:db8::1"));
+        assert!(!v6.is_match("192.0.2.10"));
+
+        // Anything else is a regex on the content
+        let regex = ContentFilter::new("^v=spf1")?;
+        assert!(matches!(regex, ContentFilter::Regex(_)));
+        assert!(regex.is_match("v=spf1 -all"));
+        assert!(!regex.is_match("192.0.2.10"));
+        assert!(ContentFilter::new("(").is_err());
+        Ok(())
+    }
+}
@@ -17,7 +17,9 @@ impl VerifyCmd {
     pub async fn run(self, opts: ConfigOpts) -> Result<()> {
         // Apply CLI configuration layering
         let cli_opts = ConfigOpts::builder().verify(Some(self.cfg)).build();
-        let opts = ConfigOpts::builder().merge(opts).merge(cli_opts).build();
+        let mut opts =
+            ConfigOpts::builder().merge(opts).merge(cli_opts).build();
+        opts.verify.resolve_token()?;
 
         // Run
         verify(&opts).await
@@ -1,9 +1,13 @@
+use crate::config::check::unknown_keys;
 use crate::config::models::{
     ConfigOpts, ConfigOptsInventory, ConfigOptsList, ConfigOptsVerify,
 };
-use anyhow::Result;
+use crate::inventory::models::InventoryFormat;
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
 
 /// A builder for configuration options.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -11,6 +15,9 @@ pub struct ConfigBuilder {
     pub verify: Option<ConfigOptsVerify>,
     pub list: Option<ConfigOptsList>,
     pub inventory: Option<ConfigOptsInventory>,
+    /// Named profiles, each overlaying the sections above when selected.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub profile: IndexMap<String, ConfigBuilder>,
 }
 
 impl ConfigBuilder {
@@ -20,6 +27,27 @@ impl ConfigBuilder {
             verify: None,
             list: None,
             inventory: None,
+            profile: IndexMap::new(),
+        }
+    }
+
+    /// Read a config file, including its profiles, if it exists.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if path.exists() {
+            debug!("configuration file found");
+            debug!("reading configuration path: '{}'", path.display());
+            let cfg_bytes =
+                std::fs::read_to_string(path).context("reading config file")?;
+            let cfg: ConfigBuilder = toml::from_str(&cfg_bytes)
+                .context("reading config file contents as TOML data")?;
+            for key in unknown_keys(&cfg_bytes)? {
+                warn!("{}: {key}", path.display());
+            }
+            Ok(Some(cfg))
+        } else {
+            debug!("configuration file not found");
+            Ok(None)
         }
     }
 
@@ -30,7 +58,13 @@ impl ConfigBuilder {
             (None, None) => None,
             (Some(val), None) | (None, Some(val)) => Some(val),
             (Some(l), Some(mut g)) => {
-                g.token = g.token.or(l.token);
+                // A layer which sets any token source overrides them all
+                if !g.has_token_source() {
+                    g.token = l.token;
+                    g.token_file = l.token_file;
+                    g.token_command = l.token_command;
+                    g.token_keyring = l.token_keyring;
+                }
                 Some(g)
             }
         };
@@ -42,6 +76,13 @@ impl ConfigBuilder {
                 g.ignore_zones = g.ignore_zones.or(l.ignore_zones);
                 g.include_records = g.include_records.or(l.include_records);
                 g.ignore_records = g.ignore_records.or(l.ignore_records);
+                g.zone_status = g.zone_status.or(l.zone_status);
+                g.types = g.types.or(l.types);
+                g.content = g.content.or(l.content);
+                g.proxied = g.proxied.or(l.proxied);
+                g.ttl = g.ttl.or(l.ttl);
+                g.pattern_syntax = g.pattern_syntax.or(l.pattern_syntax);
+                g.anchored = g.anchored.or(l.anchored);
                 Some(g)
             }
         };
@@ -51,9 +92,13 @@ impl ConfigBuilder {
             (Some(val), None) | (None, Some(val)) => Some(val),
             (Some(l), Some(mut g)) => {
                 g.path = g.path.or(l.path);
+                g.format = g.format.or(l.format);
+                g.include = g.include.or(l.include);
                 g.force_update = g.force_update.or(l.force_update);
                 g.force_prune = g.force_prune.or(l.force_prune);
                 g.watch_interval = g.watch_interval.or(l.watch_interval);
+                g.backups = g.backups.or(l.backups);
+                g.dry_run = g.dry_run.or(l.dry_run);
                 Some(g)
             }
         };
@@ -132,6 +177,24 @@ impl ConfigBuilder {
         self
     }
 
+    /// Initialize the inventory format.
+    pub fn inventory_format(
+        &mut self,
+        format: Option<InventoryFormat>,
+    ) -> &mut Self {
+        self.inventory.get_or_insert_default().format = format;
+        self
+    }
+
+    /// Initialize the inventory fragments to include.
+    pub fn inventory_include(
+        &mut self,
+        include: Option<Vec<String>>,
+    ) -> &mut Self {
+        self.inventory.get_or_insert_default().include = include;
+        self
+    }
+
     /// Initialize the inventory force update flag.
     pub fn inventory_force_update(&mut self, force: Option<bool>) -> &mut Self {
         self.inventory.get_or_insert_default().force_update = force;
@@ -153,6 +216,18 @@ impl ConfigBuilder {
         self
     }
 
+    /// Initialize the amount of backups kept when overwriting files.
+    pub fn inventory_backups(&mut self, backups: Option<usize>) -> &mut Self {
+        self.inventory.get_or_insert_default().backups = backups;
+        self
+    }
+
+    /// Initialize the dry run flag.
+    pub fn inventory_dry_run(&mut self, dry_run: Option<bool>) -> &mut Self {
+        self.inventory.get_or_insert_default().dry_run = dry_run;
+        self
+    }
+
     /// Build an configuration options model.
     pub fn build(&self) -> ConfigOpts {
         ConfigOpts {
@@ -160,6 +235,9 @@ impl ConfigBuilder {
                 let verify = self.verify.as_ref();
                 ConfigOptsVerify {
                     token: verify.and_then(|o| o.token.clone()),
+                    token_file: verify.and_then(|o| o.token_file.clone()),
+                    token_command: verify.and_then(|o| o.token_command.clone()),
+                    token_keyring: verify.and_then(|o| o.token_keyring.clone()),
                 }
             },
             list: {
@@ -170,24 +248,41 @@ impl ConfigBuilder {
                     include_records: list
                         .and_then(|o| o.include_records.clone()),
                     ignore_records: list.and_then(|o| o.ignore_records.clone()),
+                    zone_status: list.and_then(|o| o.zone_status.clone()),
+                    types: list.and_then(|o| o.types.clone()),
+                    content: list.and_then(|o| o.content.clone()),
+                    proxied: list.and_then(|o| o.proxied),
+                    ttl: list.and_then(|o| o.ttl.clone()),
+                    pattern_syntax: list.and_then(|o| o.pattern_syntax),
+                    anchored: list.and_then(|o| o.anchored),
                 }
             },
             inventory: {
                 let inventory = self.inventory.as_ref();
                 ConfigOptsInventory {
                     path: inventory.and_then(|o| o.path.clone()),
+                    format: inventory.and_then(|o| o.format),
+                    include: inventory.and_then(|o| o.include.clone()),
                     force_update: inventory.and_then(|o| o.force_update),
                     force_prune: inventory.and_then(|o| o.force_prune),
                     watch_interval: inventory.and_then(|o| o.watch_interval),
+                    backups: inventory.and_then(|o| o.backups),
+                    dry_run: inventory.and_then(|o| o.dry_run),
                 }
             },
         }
     }
 
-    /// Save the config file at the given path, overwriting if necessary.
-    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+    /// Save the config file at the given path, overwriting if necessary and
+    /// keeping the given amount of backups.
+    pub async fn save(
+        &self,
+        path: impl AsRef<Path>,
+        backups: usize,
+        dry_run: bool,
+    ) -> Result<()> {
         let toml = crate::util::encoding::as_toml(&self)?;
-        crate::util::fs::save(path, toml).await?;
+        crate::util::fs::save(path, toml, backups, dry_run).await?;
         Ok(())
     }
 }
@@ -198,6 +293,7 @@ impl From<ConfigOpts> for ConfigBuilder {
             verify: Some(opts.verify),
             list: Some(opts.list),
             inventory: Some(opts.inventory),
+            profile: IndexMap::new(),
         }
     }
 }
@@ -205,12 +301,61 @@ impl From<ConfigOpts> for ConfigBuilder {
 impl From<Option<ConfigOpts>> for ConfigBuilder {
     fn from(opts: Option<ConfigOpts>) -> Self {
         match opts {
-            None => Self {
-                verify: None,
-                list: None,
-                inventory: None,
-            },
+            None => Self::new(),
             Some(o) => o.into(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(verify: ConfigOptsVerify) -> ConfigBuilder {
+        let mut layer = ConfigBuilder::new();
+        layer.verify(Some(verify));
+        layer
+    }
+
+    #[test]
+    fn token_sources_override_each_other_as_a_whole() {
+        let file = layer(ConfigOptsVerify {
+            token_file: Some(PathBuf::from("/run/secrets/token")),
+            token_keyring: Some("home".to_string()),
+            ..Default::default()
+        });
+        let command = layer(ConfigOptsVerify {
+            token_command: Some("pass show cf".to_string()),
+            ..Default::default()
+        });
+
+        // A greater layer's source replaces every source of the lower layer
+        let mut merged = file.clone();
+        merged.merge(command);
+        let verify = merged.verify.unwrap_or_default();
+        assert_eq!(verify.token_command.as_deref(), Some("pass show cf"));
+        assert_eq!(verify.token_file, None);
+        assert_eq!(verify.token_keyring, None);
+
+        // A greater layer without a source keeps the lower layer's sources
+        let mut merged = file.clone();
+        merged.merge(layer(ConfigOptsVerify::default()));
+        let verify = merged.verify.unwrap_or_default();
+        assert_eq!(
+            verify.token_file,
+            Some(PathBuf::from("/run/secrets/token"))
+        );
+        assert_eq!(verify.token_keyring.as_deref(), Some("home"));
+
+        // So does an inline token, e.g. from the command line
+        let mut merged = file;
+        merged.merge(layer(ConfigOptsVerify {
+            token: Some("inline".to_string()),
+            ..Default::default()
+        }));
+        let verify = merged.verify.unwrap_or_default();
+        assert_eq!(verify.token.as_deref(), Some("inline"));
+        assert_eq!(verify.token_file, None);
+        assert_eq!(verify.token_keyring, None);
+    }
+}
@@ -0,0 +1,464 @@
+use crate::config::builder::ConfigBuilder;
+use crate::config::models::ConfigOpts;
+use crate::inventory::default_inventory_path;
+use crate::inventory::lint::{Issue, Severity};
+use crate::inventory::models::InventorySet;
+use anyhow::{Context, Result};
+use std::fmt::Display;
+use std::path::Path;
+
+/// The DNS record types Cloudflare supports.
+const RECORD_TYPES: &[&str] = &[
+    "A", "AAAA", "CAA", "CERT", "CNAME", "DNSKEY", "DS", "HTTPS", "LOC", "MX",
+    "NAPTR", "NS", "PTR", "SMIMEA", "SRV", "SSHFP", "SVCB", "TLSA", "TXT",
+    "URI",
+];
+
+/// The statuses a Cloudflare zone can have.
+const ZONE_STATUSES: &[&str] = &[
+    "initializing",
+    "pending",
+    "active",
+    "moved",
+    "deleted",
+    "deactivated",
+];
+
+/// A key in a config file which no option matches.
+#[derive(Clone, Debug)]
+pub struct UnknownKey {
+    /// The dotted path to the key, e.g. `inventory.interval`.
+    pub key: String,
+    pub line: Option<usize>,
+    /// The closest known key, if any is close.
+    pub suggestion: Option<String>,
+}
+
+impl Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown key '{}'", self.key)?;
+        if let Some(suggestion) = self.suggestion.as_ref() {
+            write!(f, ", did you mean '{suggestion}'?")?;
+        }
+        Ok(())
+    }
+}
+
+/// A key in a config file whose value does not match the type of its option.
+#[derive(Clone, Debug)]
+pub struct InvalidValue {
+    /// The dotted path to the key, e.g. `inventory.watch_interval`.
+    pub key: String,
+    pub line: Option<usize>,
+    /// Why the value was rejected, e.g. `invalid type: string "a", expected
+    /// u64`.
+    pub reason: String,
+}
+
+impl Display for InvalidValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid value for '{}': {}", self.key, self.reason)
+    }
+}
+
+/// Return the keys of a TOML config file whose values can not be read,
+/// including those of its `[profile.<name>]` sections. Each key is read on
+/// its own, so every invalid value is found rather than only the first.
+pub fn invalid_values(text: &str) -> Result<Vec<InvalidValue>> {
+    let mut table: toml::Table =
+        toml::from_str(text).context("reading config file contents as TOML")?;
+    let mut invalid = vec![];
+    if let Some(toml::Value::Table(profiles)) = table.remove("profile") {
+        for (name, profile) in profiles.iter() {
+            if let toml::Value::Table(profile) = profile {
+                invalid_sections(
+                    text,
+                    &format!("profile.{name}."),
+                    profile,
+                    &mut invalid,
+                );
+            }
+        }
+    }
+    invalid_sections(text, "", &table, &mut invalid);
+    invalid.sort_by_key(|value| value.line);
+    Ok(invalid)
+}
+
+/// Collect the invalid values of a table of sections, whose dotted paths
+/// start with `prefix`.
+fn invalid_sections(
+    text: &str,
+    prefix: &str,
+    table: &toml::Table,
+    invalid: &mut Vec<InvalidValue>,
+) {
+    let read = |section: &str, value: toml::Value| {
+        let mut single = toml::Table::new();
+        single.insert(section.to_string(), value);
+        toml::Value::Table(single)
+            .try_into::<ConfigBuilder>()
+            .err()
+            .map(|err| err.message().to_string())
+    };
+    for (section, value) in table.iter() {
+        let path = format!("{prefix}{section}");
+        let toml::Value::Table(keys) = value else {
+            if let Some(reason) = read(section, value.clone()) {
+                invalid.push(InvalidValue {
+                    line: line_of(text, None, &path),
+                    key: path,
+                    reason,
+                });
+            }
+            continue;
+        };
+        for (key, value) in keys.iter() {
+            let mut single = toml::Table::new();
+            single.insert(key.clone(), value.clone());
+            if let Some(reason) = read(section, toml::Value::Table(single)) {
+                invalid.push(InvalidValue {
+                    key: format!("{path}.{key}"),
+                    line: line_of(text, Some(&path), key),
+                    reason,
+                });
+            }
+        }
+    }
+}
+
+/// Return the keys of a TOML config file which no option matches, including
+/// those of its `[profile.<name>]` sections.
+pub fn unknown_keys(text: &str) -> Result<Vec<UnknownKey>> {
+    let mut table: toml::Table =
+        toml::from_str(text).context("reading config file contents as TOML")?;
+    let known = serde_json::to_value(ConfigOpts::default())
+        .context("listing known config keys")?;
+    let known = known.as_object().context("listing known config keys")?;
+    let mut unknown = vec![];
+    if let Some(profiles) = table.remove("profile") {
+        match profiles.as_table() {
+            Some(profiles) => {
+                for (name, profile) in profiles.iter() {
+                    match profile.as_table() {
+                        Some(profile) => unknown_sections(
+                            text,
+                            &format!("profile.{name}."),
+                            profile,
+                            known,
+                            &mut unknown,
+                        ),
+                        None => unknown.push(UnknownKey {
+                            key: format!("profile.{name}"),
+                            line: line_of(text, Some("profile"), name),
+                            suggestion: None,
+                        }),
+                    }
+                }
+            }
+            None => unknown.push(UnknownKey {
+                key: "profile".to_string(),
+                line: line_of(text, None, "profile"),
+                suggestion: None,
+            }),
+        }
+    }
+    unknown_sections(text, "", &table, known, &mut unknown);
+    unknown.sort_by_key(|key| key.line);
+    Ok(unknown)
+}
+
+/// Collect the unknown sections and keys of a table of sections, whose
+/// dotted paths start with `prefix`.
+fn unknown_sections(
+    text: &str,
+    prefix: &str,
+    table: &toml::Table,
+    known: &serde_json::Map<String, serde_json::Value>,
+    unknown: &mut Vec<UnknownKey>,
+) {
+    for (section, value) in table.iter() {
+        let path = format!("{prefix}{section}");
+        let Some(known_keys) = known.get(section).and_then(|v| v.as_object())
+        else {
+            let mut candidates = known.keys().cloned().collect::<Vec<_>>();
+            if prefix.is_empty() {
+                candidates.push("profile".to_string());
+            }
+            unknown.push(UnknownKey {
+                line: line_of(text, None, &path),
+                suggestion: suggest(section, candidates.iter()),
+                key: path,
+            });
+            continue;
+        };
+        let Some(keys) = value.as_table() else {
+            continue;
+        };
+        for key in keys.keys() {
+            if !known_keys.contains_key(key) {
+                unknown.push(UnknownKey {
+                    key: format!("{path}.{key}"),
+                    line: line_of(text, Some(&path), key),
+                    suggestion: suggest(key, known_keys.keys()),
+                });
+            }
+        }
+    }
+}
+
+/// Check a config file and the options in effect, returning every problem.
+pub fn check(opts: &ConfigOpts, path: &Path) -> Vec<Issue> {
+    let mut issues = vec![];
+    let mut issue = |line, severity, message: String| {
+        issues.push(Issue {
+            path: path.to_path_buf(),
+            line,
+            severity,
+            message,
+        })
+    };
+
+    // Config file
+    match std::fs::read_to_string(path) {
+        Ok(text) => match (unknown_keys(&text), invalid_values(&text)) {
+            (Ok(unknown), Ok(invalid)) => {
+                let mut found = unknown
+                    .into_iter()
+                    .map(|key| (key.line, key.to_string()))
+                    .chain(
+                        invalid
+                            .into_iter()
+                            .map(|value| (value.line, value.to_string())),
+                    )
+                    .collect::<Vec<_>>();
+                found.sort_by_key(|(line, _)| *line);
+                for (line, message) in found {
+                    issue(line, Severity::Error, message);
+                }
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                issue(None, Severity::Error, format!("{err:#}"))
+            }
+        },
+        Err(_) if !path.exists() => issue(
+            None,
+            Severity::Warning,
+            "config file not found, using defaults and environment variables"
+                .to_string(),
+        ),
+        Err(err) => {
+            issue(None, Severity::Error, format!("reading config file: {err}"))
+        }
+    }
+
+    // Token
+    let mut verify = opts.verify.clone();
+    if let Err(err) = verify.resolve_token() {
+        issue(None, Severity::Error, format!("{err:#}"));
+    }
+    match verify.token.as_deref() {
+        None if verify.has_token_source() => {}
+        None => issue(
+            None,
+            Severity::Error,
+            "no token was provided, set `verify.token`, `verify.token_file`, `verify.token_command` or `verify.token_keyring`"
+                .to_string(),
+        ),
+        Some(token) if token.trim() != token || token.is_empty() => issue(
+            None,
+            Severity::Error,
+            "token is empty or has surrounding whitespace".to_string(),
+        ),
+        Some(token)
+            if token.len() != 40
+                || !token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            issue(
+                None,
+                Severity::Warning,
+                "token does not look like a Cloudflare API token, expected 40 letters, digits, '-' or '_'"
+                    .to_string(),
+            )
+        }
+        Some(_) => {}
+    }
+
+    // Filters
+    for problem in crate::cmd::list::check_filters(opts) {
+        issue(None, Severity::Error, problem);
+    }
+    for zone_status in opts.list.zone_status.iter().flatten() {
+        if !ZONE_STATUSES.contains(&zone_status.trim().to_lowercase().as_str())
+        {
+            issue(
+                None,
+                Severity::Warning,
+                format!(
+                    "unknown zone status '{zone_status}' in `list.zone_status`, expected one of: {}",
+                    ZONE_STATUSES.join(", ")
+                ),
+            );
+        }
+    }
+    for record_type in opts.list.types.iter().flatten() {
+        if !RECORD_TYPES.contains(&record_type.trim().to_uppercase().as_str()) {
+            issue(
+                None,
+                Severity::Warning,
+                format!(
+                    "unknown record type '{record_type}' in `list.types`, expected one of: {}",
+                    RECORD_TYPES.join(", ")
+                ),
+            );
+        }
+    }
+
+    // Inventory
+    let inventory_path = opts
+        .inventory
+        .path
+        .clone()
+        .unwrap_or_else(default_inventory_path);
+    if inventory_path.is_dir() {
+        issue(
+            None,
+            Severity::Error,
+            format!(
+                "inventory path '{}' is a directory, expected a file",
+                inventory_path.display()
+            ),
+        );
+    } else if let Err(err) = InventorySet::files(opts) {
+        let message = match inventory_path.exists() {
+            true => format!("{err:#}"),
+            false => format!(
+                "inventory file '{}' not found, build one with `cddns inventory build`",
+                inventory_path.display()
+            ),
+        };
+        issue(None, Severity::Warning, message);
+    }
+
+    issues
+}
+
+/// Return the closest candidate to an unknown key, if any is close.
+pub fn suggest<'a>(
+    key: &str,
+    candidates: impl Iterator<Item = &'a String>,
+) -> Option<String> {
+    candidates
+        .map(|candidate| (distance(key, candidate), candidate))
+        .filter(|(distance, candidate)| {
+            *distance <= (key.len() / 3).max(2)
+                || candidate.contains(key)
+                || key.contains(candidate.as_str())
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Return the edit distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(
+                substitution.min(previous[j + 1] + 1).min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Return the line of a key in a TOML file, within a section if given.
+fn line_of(text: &str, section: Option<&str>, key: &str) -> Option<usize> {
+    let mut current = None;
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) =
+            line.strip_prefix('[').and_then(|l| l.split(']').next())
+        {
+            let header = header.trim();
+            current = Some(header.to_string());
+            if section.is_none()
+                && (header == key
+                    || header
+                        .strip_prefix(key)
+                        .is_some_and(|h| h.starts_with('.')))
+            {
+                return Some(idx + 1);
+            }
+            continue;
+        }
+        let assigned = line
+            .split_once('=')
+            .map(|(k, _)| k.trim().trim_matches(['"', '\'']));
+        if current.as_deref() == section && assigned == Some(key) {
+            return Some(idx + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_every_invalid_value() -> Result<()> {
+        let text = "\
+[inventory]
+watch_interval = \"soon\"
+force_update = \"yes\"
+backups = 0
+
+[profile.work.inventory]
+watch_interval = 0
+backups = -1
+";
+        let invalid = invalid_values(text)?
+            .into_iter()
+            .map(|value| (value.line, value.key))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            invalid,
+            vec![
+                (Some(2), "inventory.watch_interval".to_string()),
+                (Some(3), "inventory.force_update".to_string()),
+                (Some(8), "profile.work.inventory.backups".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn finds_unknown_keys() -> Result<()> {
+        let text = "\
+[inventory]
+interval = 5
+
+[profile.work.lists]
+types = [\"A\"]
+";
+        let unknown = unknown_keys(text)?
+            .into_iter()
+            .map(|key| key.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unknown,
+            vec![
+                "unknown key 'inventory.interval', did you mean 'watch_interval'?",
+                "unknown key 'profile.work.lists', did you mean 'list'?",
+            ]
+        );
+        Ok(())
+    }
+}
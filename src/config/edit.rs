@@ -0,0 +1,323 @@
+use crate::config::builder::ConfigBuilder;
+use crate::config::check::{suggest, UnknownKey};
+use crate::config::models::ConfigOpts;
+use anyhow::{bail, Context, Result};
+use toml_edit::{Document, Item, TableLike, Value};
+
+/// A structure-aware editor for config files, which preserves user comments,
+/// whitespace and ordering while setting and unsetting keys.
+#[derive(Clone, Debug)]
+pub struct ConfigEditor {
+    document: Document,
+}
+
+impl ConfigEditor {
+    /// Parse a config file.
+    pub fn parse(text: &str) -> Result<Self> {
+        let document = text
+            .parse::<Document>()
+            .context("parsing TOML config file")?;
+        Ok(Self { document })
+    }
+
+    /// Set a key, e.g. `inventory.watch_interval`, in the base sections or a
+    /// profile. The value is read as TOML, e.g. `30000` or `["a", "b"]`, or
+    /// else as a string, and must match the type of the option.
+    pub fn set(
+        &mut self,
+        profile: Option<&str>,
+        key: &str,
+        value: &str,
+    ) -> Result<()> {
+        let (section, field) = split_key(key)?;
+        let string = Value::from(value);
+        let candidates = match value.parse::<Value>() {
+            Ok(parsed) if !parsed.is_str() => vec![parsed, string],
+            _ => vec![string],
+        };
+        let mut error = None;
+        for mut candidate in candidates {
+            let mut document = self.document.clone();
+            let table = section_mut(&mut document, profile, section)?;
+            candidate.decor_mut().clear();
+            // Replace values in place, keeping the key's comments
+            match table.get_mut(field) {
+                Some(item) => {
+                    if let Some(old) = item.as_value() {
+                        *candidate.decor_mut() = old.decor().clone();
+                    }
+                    *item = Item::Value(candidate);
+                }
+                None => {
+                    table.insert(field, Item::Value(candidate));
+                }
+            }
+            match toml::from_str::<ConfigBuilder>(&document.to_string()) {
+                Ok(_) => {
+                    self.document = document;
+                    return Ok(());
+                }
+                Err(err) => {
+                    error.get_or_insert(err.message().to_string());
+                }
+            }
+        }
+        bail!(
+            "invalid value '{value}' for '{key}': {}",
+            error.unwrap_or_default()
+        )
+    }
+
+    /// Remove a key, e.g. `inventory.watch_interval`, from the base sections
+    /// or a profile, returning whether it was set.
+    pub fn unset(&mut self, profile: Option<&str>, key: &str) -> Result<bool> {
+        let (section, field) = split_key(key)?;
+        let mut table = self.document.as_table_mut() as &mut dyn TableLike;
+        if let Some(name) = profile {
+            for key in ["profile", name] {
+                match table.get_mut(key).and_then(Item::as_table_like_mut) {
+                    Some(inner) => table = inner,
+                    None => return Ok(false),
+                }
+            }
+        }
+        let Some(table) =
+            table.get_mut(section).and_then(Item::as_table_like_mut)
+        else {
+            return Ok(false);
+        };
+        // The key's own comments are removed with it
+        Ok(table.remove(field).is_some())
+    }
+
+    /// Returns whether a `[profile.<name>]` section exists.
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.document
+            .get("profile")
+            .and_then(Item::as_table_like)
+            .is_some_and(|profiles| profiles.contains_key(name))
+    }
+
+    /// Replace a profile with the options of a config, keeping the rest of
+    /// the file as is.
+    pub fn set_profile(
+        &mut self,
+        name: &str,
+        config: &ConfigBuilder,
+    ) -> Result<()> {
+        let built = crate::util::encoding::as_toml(config)?
+            .parse::<Document>()
+            .context("parsing built config")?;
+        let separate = !self.document.to_string().trim().is_empty();
+        let mut profile = toml_edit::Table::new();
+        profile.set_implicit(true);
+        for (section, item) in built.iter() {
+            let Some(options) = item.as_table() else {
+                continue;
+            };
+            // Copy the options into a new table, placed after the others
+            let mut table = toml_edit::Table::new();
+            for (key, value) in options.iter() {
+                table.insert(key, value.clone());
+            }
+            if separate || !profile.is_empty() {
+                table.decor_mut().set_prefix("\n");
+            }
+            profile.insert(section, Item::Table(table));
+        }
+        let root = self.document.as_table_mut();
+        if !root.contains_key("profile") {
+            let mut profiles = toml_edit::Table::new();
+            profiles.set_implicit(true);
+            root.insert("profile", Item::Table(profiles));
+        }
+        root.get_mut("profile")
+            .and_then(Item::as_table_like_mut)
+            .context("'profile' is not a table")?
+            .insert(name, Item::Table(profile));
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ConfigEditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+/// Split a dotted key, e.g. `inventory.watch_interval`, into its section and
+/// field, if it names an option.
+pub fn split_key(key: &str) -> Result<(&str, &str)> {
+    let known = serde_json::to_value(ConfigOpts::default())
+        .context("listing known config keys")?;
+    let known = known.as_object().context("listing known config keys")?;
+    let unknown = |suggestion| UnknownKey {
+        key: key.to_string(),
+        line: None,
+        suggestion,
+    };
+    let Some((section, field)) = key.split_once('.') else {
+        if known.contains_key(key) {
+            bail!("'{key}' is a section, expected a key such as '{key}.<key>'");
+        }
+        bail!(unknown(suggest(key, known.keys())));
+    };
+    let Some(fields) = known.get(section).and_then(|v| v.as_object()) else {
+        bail!(unknown(suggest(section, known.keys())));
+    };
+    if !fields.contains_key(field) {
+        bail!(unknown(suggest(field, fields.keys())));
+    }
+    Ok((section, field))
+}
+
+/// Return a section of the base config or a profile, creating it if needed.
+fn section_mut<'a>(
+    document: &'a mut Document,
+    profile: Option<&str>,
+    section: &str,
+) -> Result<&'a mut dyn TableLike> {
+    let mut table = document.as_table_mut() as &mut dyn TableLike;
+    let path = match profile {
+        Some(name) => vec![("profile", true), (name, true), (section, false)],
+        None => vec![(section, false)],
+    };
+    for (key, implicit) in path {
+        if !table.contains_key(key) {
+            let mut inner = toml_edit::Table::new();
+            inner.set_implicit(implicit);
+            table.insert(key, Item::Table(inner));
+        }
+        table = table
+            .get_mut(key)
+            .and_then(Item::as_table_like_mut)
+            .with_context(|| format!("'{key}' is not a table"))?;
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_profile_keeps_the_file() -> Result<()> {
+        let text = "\
+# Base options
+[inventory]
+backups = 3 # keep a few
+
+[profile.home.inventory]
+path = \"home.yml\"
+";
+        let mut config = ConfigBuilder::new();
+        config.inventory_watch_interval(Some(0));
+        let mut editor = ConfigEditor::parse(text)?;
+        assert!(editor.has_profile("home"));
+        assert!(!editor.has_profile("work"));
+        editor.set_profile("work", &config)?;
+        assert_eq!(
+            editor.to_string(),
+            "\
+# Base options
+[inventory]
+backups = 3 # keep a few
+
+[profile.home.inventory]
+path = \"home.yml\"
+
+[profile.work.inventory]
+watch_interval = 0
+"
+        );
+
+        // Profiles are replaced in place
+        config.inventory_backups(Some(1));
+        editor.set_profile("home", &config)?;
+        assert_eq!(
+            editor.to_string(),
+            "\
+# Base options
+[inventory]
+backups = 3 # keep a few
+
+[profile.home.inventory]
+watch_interval = 0
+backups = 1
+
+[profile.work.inventory]
+watch_interval = 0
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_profile_in_an_empty_file() -> Result<()> {
+        let mut config = ConfigBuilder::new();
+        config.inventory_backups(Some(1));
+        let mut editor = ConfigEditor::parse("")?;
+        editor.set_profile("work", &config)?;
+        assert_eq!(
+            editor.to_string(),
+            "[profile.work.inventory]\nbackups = 1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_keeps_comments() -> Result<()> {
+        let text = "\
+[inventory]
+# How often to check
+watch_interval = 30000 # 30s
+";
+        let mut editor = ConfigEditor::parse(text)?;
+        editor.set(None, "inventory.watch_interval", "0")?;
+        editor.set(None, "inventory.backups", "2")?;
+        assert_eq!(
+            editor.to_string(),
+            "\
+[inventory]
+# How often to check
+watch_interval = 0 # 30s
+backups = 2
+"
+        );
+        assert!(editor.set(None, "inventory.backups", "-1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unset_removes_the_key_comments() -> Result<()> {
+        let text = "\
+[inventory]
+# How often to check
+watch_interval = 30000
+
+# Backups of overwritten files
+backups = 3
+
+[profile.work.inventory]
+# Work backups
+backups = 1
+";
+        let mut editor = ConfigEditor::parse(text)?;
+        assert!(editor.unset(None, "inventory.watch_interval")?);
+        assert!(editor.unset(Some("work"), "inventory.backups")?);
+        assert!(!editor.unset(None, "inventory.watch_interval")?);
+        assert!(!editor.unset(Some("home"), "inventory.backups")?);
+        assert_eq!(
+            editor.to_string(),
+            "\
+[inventory]
+
+# Backups of overwritten files
+backups = 3
+
+[profile.work.inventory]
+"
+        );
+        Ok(())
+    }
+}
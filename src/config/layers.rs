@@ -0,0 +1,175 @@
+use crate::config::builder::ConfigBuilder;
+use crate::config::default_config_path;
+use crate::config::models::ConfigOpts;
+use anyhow::{Context, Result};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// The layers of configuration, from lowest to highest precedence.
+#[derive(Clone, Debug)]
+pub struct ConfigLayers {
+    /// Static defaults.
+    pub default: ConfigOpts,
+    /// The config file, if it exists.
+    pub file: Option<ConfigOpts>,
+    /// The name of the selected profile, if any.
+    pub profile_name: Option<String>,
+    /// The selected `[profile.<name>]` sections of the config file.
+    pub profile: Option<ConfigOpts>,
+    /// `CDDNS_*` environment variables.
+    pub env: ConfigOpts,
+    /// Global command line options.
+    pub cli: ConfigOpts,
+}
+
+/// The layer a configuration value comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File,
+    Profile,
+    Env,
+    Cli,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File => write!(f, "file"),
+            Source::Profile => write!(f, "profile"),
+            Source::Env => write!(f, "env"),
+            Source::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+impl ConfigLayers {
+    /// Read the file, profile and environment layers beneath the command line
+    /// options.
+    pub fn load(
+        path: Option<PathBuf>,
+        profile_name: Option<String>,
+        cli: ConfigOpts,
+    ) -> Result<Self> {
+        Self::read(path, profile_name, cli, true)
+    }
+
+    /// Read the layers like [`ConfigLayers::load`], but skip a config file or
+    /// profile which can not be read, so `config check` can report why.
+    pub fn load_lenient(
+        path: Option<PathBuf>,
+        profile_name: Option<String>,
+        cli: ConfigOpts,
+    ) -> Result<Self> {
+        Self::read(path, profile_name, cli, false)
+    }
+
+    fn read(
+        path: Option<PathBuf>,
+        profile_name: Option<String>,
+        cli: ConfigOpts,
+        strict: bool,
+    ) -> Result<Self> {
+        let path = path.unwrap_or_else(default_config_path);
+        let file = match ConfigBuilder::from_file(&path) {
+            Ok(file) => file,
+            Err(err) if !strict => {
+                debug!("skipping config file: {err:#}");
+                None
+            }
+            Err(err) => return Err(err),
+        };
+        let profile = match profile_name.as_deref() {
+            Some(name) => {
+                let profile = Self::profile(&path, file.as_ref(), name);
+                match profile {
+                    Ok(profile) => Some(profile),
+                    Err(err) if !strict => {
+                        debug!("skipping profile: {err:#}");
+                        None
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            None => None,
+        };
+        Ok(Self {
+            default: ConfigOpts::default(),
+            file: file.map(|file| file.build()),
+            profile_name,
+            profile,
+            env: ConfigOpts::from_env()?,
+            cli,
+        })
+    }
+
+    /// Return a `[profile.<name>]` section of the config file.
+    fn profile(
+        path: &Path,
+        file: Option<&ConfigBuilder>,
+        name: &str,
+    ) -> Result<ConfigOpts> {
+        let file = file.with_context(|| {
+            format!(
+                "profile '{name}' was selected, but config file '{}' does not exist",
+                path.display()
+            )
+        })?;
+        let profile = file.profile.get(name).with_context(|| {
+            let names = file.profile.keys().cloned().collect::<Vec<_>>();
+            match names.is_empty() {
+                true => format!(
+                    "profile '{name}' not found, '{}' has no profiles",
+                    path.display()
+                ),
+                false => format!(
+                    "profile '{name}' not found in '{}', expected one of: {}",
+                    path.display(),
+                    names.join(", ")
+                ),
+            }
+        })?;
+        Ok(profile.build())
+    }
+
+    /// Merge the layers into the effective configuration.
+    pub fn merge(&self) -> ConfigOpts {
+        ConfigOpts::builder()
+            .merge(self.default.clone())
+            .merge(self.file.clone())
+            .merge(self.profile.clone())
+            .merge(self.env.clone())
+            .merge(self.cli.clone())
+            .build()
+    }
+
+    /// Return the layer the value of a key, e.g. `list.match`, comes from, or
+    /// `None` if it is unset.
+    pub fn source(&self, key: &str) -> Option<Source> {
+        let pointer = format!("/{}", key.replace('.', "/"));
+        let is_set = |opts: &ConfigOpts| {
+            serde_json::to_value(opts)
+                .ok()
+                .and_then(|value| value.pointer(&pointer).cloned())
+                .is_some_and(|value| !value.is_null())
+        };
+        [
+            (Source::Cli, Some(&self.cli)),
+            (Source::Env, Some(&self.env)),
+            (Source::Profile, self.profile.as_ref()),
+            (Source::File, self.file.as_ref()),
+            (Source::Default, Some(&self.default)),
+        ]
+        .into_iter()
+        .find(|(_, layer)| layer.is_some_and(is_set))
+        .map(|(source, _)| source)
+    }
+}
+
+/// Return the environment variable which sets a key, e.g. `CDDNS_LIST_MATCH`
+/// for `list.match`.
+pub fn env_var(key: &str) -> String {
+    format!("CDDNS_{}", key.replace('.', "_").to_uppercase())
+}
@@ -1,11 +1,16 @@
 //! cddns configuration.
 //!
-//! cddns takes the typical layered configuration approach. There are 3 layers.
-//! The config file is the base, which is then superseded by environment
-//! variables, which are finally superseded by CLI arguments and options.
+//! cddns takes the typical layered configuration approach. There are 4 layers.
+//! The config file is the base, which is overlaid by the selected profile of
+//! the config file, if any, which is then superseded by environment variables,
+//! which are finally superseded by CLI arguments and options.
 
 pub mod builder;
+pub mod check;
+pub mod edit;
+pub mod layers;
 pub mod models;
+pub mod token;
 
 /// Return the default configuration path, depending on the host OS.
 ///
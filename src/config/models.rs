@@ -1,12 +1,12 @@
 use crate::config::builder::ConfigBuilder;
-use crate::config::default_config_path;
 use crate::inventory::default_inventory_path;
+use crate::inventory::models::InventoryFormat;
+use crate::util::pattern::PatternSyntax;
 use anyhow::{Context, Result};
 use clap::Args;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::{fmt::Debug, fmt::Display};
-use tracing::debug;
 
 /// The model of all configuration options which can be saved in a config file.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -20,18 +20,29 @@ impl Default for ConfigOpts {
     /// Static default configuration options.
     fn default() -> Self {
         Self {
-            verify: ConfigOptsVerify { token: None },
+            verify: ConfigOptsVerify::default(),
             list: ConfigOptsList {
-                include_zones: Some(vec![".*".to_string()]),
+                include_zones: Some(vec![]),
                 ignore_zones: Some(vec![]),
-                include_records: Some(vec![".*".to_string()]),
+                include_records: Some(vec![]),
                 ignore_records: Some(vec![]),
+                zone_status: None,
+                types: None,
+                content: None,
+                proxied: None,
+                ttl: None,
+                pattern_syntax: Some(PatternSyntax::default()),
+                anchored: Some(false),
             },
             inventory: ConfigOptsInventory {
                 path: Some(default_inventory_path()),
+                format: None,
+                include: Some(vec![]),
                 force_update: Some(false),
                 force_prune: Some(false),
                 watch_interval: Some(30_000),
+                backups: Some(3),
+                dry_run: Some(false),
             },
         }
     }
@@ -43,23 +54,6 @@ impl ConfigOpts {
         ConfigBuilder::new()
     }
 
-    /// Read runtime config from a target path.
-    pub fn from_file(path: Option<PathBuf>) -> Result<Option<Self>> {
-        let path = path.unwrap_or(default_config_path());
-        if path.exists() {
-            debug!("configuration file found");
-            debug!("reading configuration path: '{}'", path.display());
-            let cfg_bytes =
-                std::fs::read_to_string(path).context("reading config file")?;
-            let cfg: ConfigBuilder = toml::from_str(&cfg_bytes)
-                .context("reading config file contents as TOML data")?;
-            Ok(Some(cfg.build()))
-        } else {
-            debug!("configuration file not found");
-            Ok(None)
-        }
-    }
-
     /// Read runtime config from environment variables.
     pub fn from_env() -> Result<Self> {
         Ok(ConfigOpts {
@@ -90,56 +84,158 @@ where
     }
 }
 
-impl Display for ConfigOpts {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        try {
-            // Verify
-            writeln!(f, "Token: {}", __display(self.verify.token.as_ref()))?;
-
-            // List
-            writeln!(
-                f,
-                "Include zones: {}",
-                __display(self.list.include_zones.as_ref())
-            )?;
-            writeln!(
-                f,
-                "Ignore zones: {}",
-                __display(self.list.ignore_zones.as_ref())
-            )?;
-            writeln!(
-                f,
-                "Include records: {}",
-                __display(self.list.include_records.as_ref())
-            )?;
-            writeln!(
-                f,
-                "Ignore records: {}",
-                __display(self.list.ignore_records.as_ref())
-            )?;
+impl ConfigOpts {
+    /// Return each option as a label, its key, e.g. `list.match`, and its
+    /// value for display. Secrets are redacted unless revealed.
+    pub fn entries(
+        &self,
+        reveal: bool,
+    ) -> Vec<(&'static str, &'static str, String)> {
+        vec![
+            (
+                "Token",
+                "verify.token",
+                __display(
+                    self.verify
+                        .token
+                        .as_ref()
+                        .map(|token| match reveal {
+                            true => token.clone(),
+                            false => redact(token),
+                        })
+                        .as_ref(),
+                ),
+            ),
+            (
+                "Token file",
+                "verify.token_file",
+                __display(self.verify.token_file.as_ref()),
+            ),
+            (
+                "Token command",
+                "verify.token_command",
+                __display(self.verify.token_command.as_ref()),
+            ),
+            (
+                "Token keyring",
+                "verify.token_keyring",
+                __display(self.verify.token_keyring.as_ref()),
+            ),
+            (
+                "Include zones",
+                "list.include_zones",
+                __display(self.list.include_zones.as_ref()),
+            ),
+            (
+                "Ignore zones",
+                "list.ignore_zones",
+                __display(self.list.ignore_zones.as_ref()),
+            ),
+            (
+                "Include records",
+                "list.include_records",
+                __display(self.list.include_records.as_ref()),
+            ),
+            (
+                "Ignore records",
+                "list.ignore_records",
+                __display(self.list.ignore_records.as_ref()),
+            ),
+            (
+                "Zone statuses",
+                "list.zone_status",
+                __display(self.list.zone_status.as_ref()),
+            ),
+            (
+                "Record types",
+                "list.types",
+                __display(self.list.types.as_ref()),
+            ),
+            (
+                "Record content",
+                "list.content",
+                __display(self.list.content.as_ref()),
+            ),
+            (
+                "Proxied records",
+                "list.proxied",
+                __display(self.list.proxied.as_ref()),
+            ),
+            ("Record TTL", "list.ttl", __display(self.list.ttl.as_ref())),
+            (
+                "Pattern syntax",
+                "list.match",
+                __display(self.list.pattern_syntax.as_ref()),
+            ),
+            (
+                "Anchored patterns",
+                "list.anchored",
+                __display(self.list.anchored.as_ref()),
+            ),
+            (
+                "Inventory path",
+                "inventory.path",
+                __display(self.inventory.path.as_ref()),
+            ),
+            (
+                "Inventory format",
+                "inventory.format",
+                __display(self.inventory.format.as_ref()),
+            ),
+            (
+                "Inventory includes",
+                "inventory.include",
+                __display(self.inventory.include.as_ref()),
+            ),
+            (
+                "Force update without user prompt",
+                "inventory.force_update",
+                __display(self.inventory.force_update.as_ref()),
+            ),
+            (
+                "Force prune without user prompt",
+                "inventory.force_prune",
+                __display(self.inventory.force_prune.as_ref()),
+            ),
+            (
+                "Watch interval",
+                "inventory.watch_interval",
+                __display(self.inventory.watch_interval.as_ref()),
+            ),
+            (
+                "Backups",
+                "inventory.backups",
+                __display(self.inventory.backups.as_ref()),
+            ),
+            (
+                "Dry run",
+                "inventory.dry_run",
+                __display(self.inventory.dry_run.as_ref()),
+            ),
+        ]
+    }
+}
 
-            // Inventory
-            writeln!(
-                f,
-                "Inventory path: {}",
-                __display(self.inventory.path.as_ref())
-            )?;
-            writeln!(
-                f,
-                "Force update without user prompt: {}",
-                __display(self.inventory.force_update.as_ref())
-            )?;
-            writeln!(
-                f,
-                "Force prune without user prompt: {}",
-                __display(self.inventory.force_prune.as_ref())
-            )?;
-            write!(
-                f,
-                "Watch interval: {}",
-                __display(self.inventory.watch_interval.as_ref())
-            )?;
+/// Mask a secret, keeping its last 4 characters if it is long enough.
+pub fn redact(secret: &str) -> String {
+    let chars = secret.chars().collect::<Vec<char>>();
+    match chars.len() >= 16 {
+        true => {
+            let last = chars[chars.len() - 4..].iter().collect::<String>();
+            format!("****{last}")
         }
+        false => "****".to_string(),
+    }
+}
+
+impl Display for ConfigOpts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .entries(false)
+            .into_iter()
+            .map(|(label, _, value)| format!("{label}: {value}"))
+            .collect::<Vec<String>>();
+        write!(f, "{}", lines.join("\n"))
     }
 }
 
@@ -149,40 +245,92 @@ pub struct ConfigOptsVerify {
     // Your Cloudflare API key token.
     #[clap(short, long, env = "CDDNS_VERIFY_TOKEN", value_name = "token")]
     pub token: Option<String>,
+    /// Read the token from a file, e.g. a Docker secret or systemd credential.
+    #[clap(long, env = "CDDNS_VERIFY_TOKEN_FILE", value_name = "file")]
+    pub token_file: Option<PathBuf>,
+    /// Read the token from the output of a shell command, e.g. `pass show cf`.
+    #[clap(long, env = "CDDNS_VERIFY_TOKEN_COMMAND", value_name = "command")]
+    pub token_command: Option<String>,
+    /// Read the token from the Secret Service keyring item with this username
+    /// and the service `cddns`. Requires the `keyring` feature.
+    #[clap(long, env = "CDDNS_VERIFY_TOKEN_KEYRING", value_name = "username")]
+    pub token_keyring: Option<String>,
 }
 
 /// Config options for the list system.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, Args)]
 pub struct ConfigOptsList {
-    /// Include cloudflare zones by regex. [default: all]
+    /// Include cloudflare zones matching any pattern. [default: all]
     #[clap(
         long,
         value_name = "pattern1,pattern2,..",
         env = "CDDNS_LIST_INCLUDE_ZONES"
     )]
     pub include_zones: Option<Vec<String>>,
-    /// Ignore cloudflare zones by regex. [default: none]
+    /// Ignore cloudflare zones matching any pattern. [default: none]
     #[clap(
         long,
         value_name = "pattern1,pattern2,..",
         env = "CDDNS_LIST_IGNORE_ZONES"
     )]
     pub ignore_zones: Option<Vec<String>>,
+    /// Include cloudflare zones with one of these statuses, e.g. active or
+    /// pending. [default: all]
+    #[clap(
+        long,
+        value_name = "status1,status2,..",
+        value_delimiter = ',',
+        env = "CDDNS_LIST_ZONE_STATUS"
+    )]
+    pub zone_status: Option<Vec<String>>,
 
-    /// Include cloudflare zone records by regex. [default: all]
+    /// Include cloudflare zone records matching any pattern. [default: all]
     #[clap(
         long,
         value_name = "pattern1,pattern2,..",
         env = "CDDNS_LIST_INCLUDE_RECORDS"
     )]
     pub include_records: Option<Vec<String>>,
-    /// Ignore cloudflare zone records by regex. [default: none]
+    /// Ignore cloudflare zone records matching any pattern. [default: none]
     #[clap(
         long,
         value_name = "pattern1,pattern2,..",
         env = "CDDNS_LIST_IGNORE_RECORDS"
     )]
     pub ignore_records: Option<Vec<String>>,
+    /// Include records of these types, e.g. A, CNAME or TXT. [default: all]
+    #[clap(
+        long = "type",
+        value_name = "type1,type2,..",
+        value_delimiter = ',',
+        env = "CDDNS_LIST_TYPES"
+    )]
+    pub types: Option<Vec<String>>,
+    /// Include records whose content matches a regex or is within a CIDR
+    /// range, e.g. `^10\.` or `10.0.0.0/8`. [default: all]
+    #[clap(long, value_name = "pattern|cidr", env = "CDDNS_LIST_CONTENT")]
+    pub content: Option<Vec<String>>,
+    /// Include only proxied, or only DNS-only, records. [default: both]
+    #[clap(long, value_name = "boolean", env = "CDDNS_LIST_PROXIED")]
+    pub proxied: Option<bool>,
+    /// Include records with a TTL in seconds, `auto`, or a range of seconds
+    /// such as `60..300` or `3600..`. [default: all]
+    #[clap(long, value_name = "ttl", env = "CDDNS_LIST_TTL")]
+    pub ttl: Option<String>,
+    /// The syntax of zone and record patterns without a `glob:` or `regex:`
+    /// prefix. [default: regex]
+    #[clap(
+        long = "match",
+        value_enum,
+        value_name = "syntax",
+        env = "CDDNS_LIST_MATCH"
+    )]
+    #[serde(rename = "match")]
+    pub pattern_syntax: Option<PatternSyntax>,
+    /// Anchor regex patterns to match whole zone and record names and ids.
+    /// [default: false]
+    #[clap(long, value_name = "boolean", env = "CDDNS_LIST_ANCHORED")]
+    pub anchored: Option<bool>,
 }
 
 /// Config options for the inventory system.
@@ -191,6 +339,22 @@ pub struct ConfigOptsInventory {
     /// The path to the inventory file.
     #[clap(short, long, env = "CDDNS_INVENTORY_PATH", value_name = "file")]
     pub path: Option<PathBuf>,
+    /// The format of the inventory file, detected from the extension if unset.
+    #[clap(
+        long,
+        value_enum,
+        env = "CDDNS_INVENTORY_FORMAT",
+        value_name = "format"
+    )]
+    pub format: Option<InventoryFormat>,
+    /// Inventory fragments to merge, as files, directories or globs. An
+    /// `inventory.d` directory beside the inventory file is always included.
+    #[clap(
+        long,
+        value_name = "path1,path2,..",
+        env = "CDDNS_INVENTORY_INCLUDE"
+    )]
+    pub include: Option<Vec<String>>,
     /// Skip prompts asking to update outdated DNS records.
     #[clap(long, env = "CDDNS_INVENTORY_FORCE_UPDATE", value_name = "boolean")]
     pub force_update: Option<bool>,
@@ -205,4 +369,12 @@ pub struct ConfigOptsInventory {
         env = "CDDNS_INVENTORY_WATCH_INTERVAL"
     )]
     pub watch_interval: Option<u64>,
+    /// The number of timestamped backups kept when overwriting files, which
+    /// applies to the config file as well as inventory files.
+    #[clap(long, value_name = "amount", env = "CDDNS_INVENTORY_BACKUPS")]
+    pub backups: Option<usize>,
+    /// Log the changes to Cloudflare records and files, without making them.
+    /// Set on the command line with the global `--dry-run` flag.
+    #[clap(skip)]
+    pub dry_run: Option<bool>,
 }
@@ -0,0 +1,228 @@
+use crate::config::models::ConfigOptsVerify;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+use tracing::debug;
+
+impl ConfigOptsVerify {
+    /// Returns whether any source of the token is set.
+    pub fn has_token_source(&self) -> bool {
+        self.token.is_some()
+            || self.token_file.is_some()
+            || self.token_command.is_some()
+            || self.token_keyring.is_some()
+    }
+
+    /// Resolve the token from the first source set, in order: `token`,
+    /// `token_file`, `token_command`, then `token_keyring`.
+    pub fn resolve_token(&mut self) -> Result<()> {
+        if self.token.is_some() {
+            return Ok(());
+        }
+        let token = if let Some(path) = self.token_file.as_ref() {
+            debug!("reading token from file '{}'", path.display());
+            from_file(path)?
+        } else if let Some(command) = self.token_command.as_ref() {
+            debug!("reading token from command");
+            from_command(command)?
+        } else if let Some(account) = self.token_keyring.as_ref() {
+            debug!("reading token from keyring");
+            from_keyring(account)?
+        } else {
+            return Ok(());
+        };
+        self.token = Some(token);
+        Ok(())
+    }
+}
+
+/// Read a token from a file, such as a Docker or systemd credential.
+fn from_file(path: &Path) -> Result<String> {
+    let token = std::fs::read_to_string(path)
+        .with_context(|| format!("reading token file '{}'", path.display()))?;
+    nonempty(token)
+        .with_context(|| format!("token file '{}' is empty", path.display()))
+}
+
+/// Read a token from the output of a shell command, e.g. `pass show cf`.
+fn from_command(command: &str) -> Result<String> {
+    let output = match cfg!(windows) {
+        true => Command::new("cmd").args(["/C", command]).output(),
+        false => Command::new("sh").args(["-c", command]).output(),
+    }
+    .with_context(|| format!("running token command '{command}'"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.trim() {
+            "" => {
+                bail!("token command '{command}' failed with {}", output.status)
+            }
+            stderr => bail!(
+                "token command '{command}' failed with {}: {stderr}",
+                output.status
+            ),
+        }
+    }
+    let token = String::from_utf8(output.stdout).with_context(|| {
+        format!("token command '{command}' printed invalid UTF-8")
+    })?;
+    nonempty(token)
+        .with_context(|| format!("token command '{command}' printed nothing"))
+}
+
+/// Read a token from the Secret Service keyring, stored with the attributes
+/// `service=cddns` and `username=<account>`.
+#[cfg(feature = "keyring")]
+fn from_keyring(account: &str) -> Result<String> {
+    use dbus::arg::{RefArg, Variant};
+    use dbus::blocking::Connection;
+    use dbus::Path;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    const BUS: &str = "org.freedesktop.secrets";
+    let timeout = Duration::from_secs(5);
+    let conn = Connection::new_session()
+        .context("connecting to the session bus for the keyring")?;
+    let service = conn.with_proxy(BUS, "/org/freedesktop/secrets", timeout);
+    let (_, session): (Variant<Box<dyn RefArg>>, Path) = service
+        .method_call(
+            "org.freedesktop.Secret.Service",
+            "OpenSession",
+            ("plain", Variant("")),
+        )
+        .context("opening a keyring session")?;
+    let attributes =
+        HashMap::from([("service", "cddns"), ("username", account)]);
+    let (unlocked, locked): (Vec<Path>, Vec<Path>) = service
+        .method_call(
+            "org.freedesktop.Secret.Service",
+            "SearchItems",
+            (attributes,),
+        )
+        .context("searching the keyring")?;
+    let Some(item) = unlocked.first() else {
+        match locked.is_empty() {
+            true => bail!("no keyring item found for service 'cddns' and username '{account}'"),
+            false => bail!("the keyring item for username '{account}' is locked, unlock your keyring and try again"),
+        }
+    };
+    let ((_, _, secret, _),): ((Path, Vec<u8>, Vec<u8>, String),) = conn
+        .with_proxy(BUS, item, timeout)
+        .method_call("org.freedesktop.Secret.Item", "GetSecret", (session,))
+        .context("reading the keyring item")?;
+    let token =
+        String::from_utf8(secret).context("keyring item is invalid UTF-8")?;
+    nonempty(token).context("keyring item is empty")
+}
+
+/// Read a token from the Secret Service keyring, which requires the
+/// `keyring` feature.
+#[cfg(not(feature = "keyring"))]
+fn from_keyring(_account: &str) -> Result<String> {
+    bail!("reading the token from the keyring requires cddns to be built with the `keyring` feature")
+}
+
+/// Return a token without surrounding whitespace, unless it is empty.
+fn nonempty(token: String) -> Option<String> {
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &str) -> Result<std::path::PathBuf> {
+        let path = std::env::temp_dir()
+            .join(format!("cddns-token-{name}-{}", std::process::id()));
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    #[test]
+    fn reads_trimmed_token_files() -> Result<()> {
+        let path = temp_file("trimmed", "  secret\n\n")?;
+        let token = from_file(&path);
+        std::fs::remove_file(&path)?;
+        assert_eq!(token?, "secret");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_empty_token_files() -> Result<()> {
+        let path = temp_file("empty", " \n")?;
+        let token = from_file(&path);
+        std::fs::remove_file(&path)?;
+        let err = token.err().map(|err| err.to_string());
+        assert_eq!(
+            err,
+            Some(format!("token file '{}' is empty", path.display()))
+        );
+
+        // A missing file is an error too
+        assert!(from_file(&path).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_trimmed_command_output() -> Result<()> {
+        assert_eq!(from_command("printf '  secret\\n'")?, "secret");
+        let err = from_command("true").err().map(|err| err.to_string());
+        assert_eq!(
+            err.as_deref(),
+            Some("token command 'true' printed nothing")
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_failed_commands() {
+        let err = from_command("echo locked >&2; exit 3")
+            .err()
+            .map(|err| err.to_string());
+        assert_eq!(
+            err.as_deref(),
+            Some("token command 'echo locked >&2; exit 3' failed with exit status: 3: locked")
+        );
+        let err = from_command("echo secret; exit 1")
+            .err()
+            .map(|err| err.to_string());
+        assert_eq!(
+            err.as_deref(),
+            Some("token command 'echo secret; exit 1' failed with exit status: 1")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_the_first_source_set() -> Result<()> {
+        let path = temp_file("first", "from-file")?;
+        let mut verify = ConfigOptsVerify {
+            token: Some("inline".to_string()),
+            token_file: Some(path.clone()),
+            token_command: Some("echo from-command".to_string()),
+            token_keyring: Some("unused".to_string()),
+        };
+        let inline = verify.resolve_token().map(|_| verify.token.clone());
+        verify.token = None;
+        let file = verify.resolve_token().map(|_| verify.token.clone());
+        verify.token = None;
+        verify.token_file = None;
+        let command = verify.resolve_token().map(|_| verify.token.clone());
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(inline?.as_deref(), Some("inline"));
+        assert_eq!(file?.as_deref(), Some("from-file"));
+        assert_eq!(command?.as_deref(), Some("from-command"));
+
+        // Without any source, there is no token
+        let mut verify = ConfigOptsVerify::default();
+        assert!(!verify.has_token_source());
+        verify.resolve_token()?;
+        assert_eq!(verify.token, None);
+        Ok(())
+    }
+}
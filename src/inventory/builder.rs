@@ -1,5 +1,5 @@
 use crate::inventory::models::Inventory;
-use crate::inventory::models::InventoryData;
+use crate::inventory::models::{InventoryData, InventoryFormat};
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
 
@@ -8,6 +8,8 @@ use std::path::{Path, PathBuf};
 pub struct InventoryBuilder {
     path: Option<PathBuf>,
     data: Option<InventoryData>,
+    source: Option<String>,
+    format: Option<InventoryFormat>,
 }
 
 impl InventoryBuilder {
@@ -16,6 +18,8 @@ impl InventoryBuilder {
         Self {
             path: None,
             data: None,
+            source: None,
+            format: None,
         }
     }
 
@@ -25,29 +29,48 @@ impl InventoryBuilder {
         self
     }
 
+    /// Initialize the inventory's format. If unset, the format is detected
+    /// from the path's extension.
+    pub fn format(mut self, format: Option<InventoryFormat>) -> Self {
+        self.format = format.or(self.format);
+        self
+    }
+
     /// Initialize inventory with data.
     pub fn with_data(mut self, data: InventoryData) -> Self {
         self.data.replace(data);
         self
     }
 
-    /// Initialize inventory data from bytes.
+    /// Initialize inventory data from bytes, keeping the source to preserve
+    /// comments and ordering on save.
     pub fn with_bytes<'a>(
         mut self,
         bytes: impl Into<&'a [u8]>,
     ) -> Result<Self> {
+        let bytes = bytes.into();
+        let format =
+            InventoryFormat::resolve(self.format, self.path.as_deref());
         self.data.replace(
-            serde_yaml::from_slice(bytes.into())
+            format
+                .decode(bytes)
                 .context("deserializing inventory from bytes")?,
         );
+        self.format.replace(format);
+        self.source = std::str::from_utf8(bytes).ok().map(str::to_string);
         Ok(self)
     }
 
     /// Build an inventory model.
     pub fn build(self) -> Result<Inventory> {
+        let format =
+            InventoryFormat::resolve(self.format, self.path.as_deref());
         Ok(Inventory {
             path: self.path.context("uninitalized path")?,
             data: self.data.context("uninitialized inventory data")?,
+            source: self.source,
+            format,
+            expanded: false,
         })
     }
 }
@@ -0,0 +1,1411 @@
+use crate::cloudflare::models::{Record, Zone};
+use crate::inventory::models::{
+    InventoryData, InventoryFormat, InventoryRecord,
+};
+use anyhow::{bail, Context, Result};
+
+/// A structure-aware editor for YAML inventory files, which preserves user
+/// comments, blank lines and ordering while editing.
+///
+/// Inventory files are a mapping of zones to sequences of records. The editor
+/// understands block sequences (`- record`), flow sequences (`[a, b]`),
+/// detailed records as flow or block mappings (`- {name: a, id: b, type: A}`)
+/// and empty zones. Anything else is rejected, so callers can fall back to
+/// rewriting the file.
+#[derive(Clone, Debug)]
+pub struct YamlEditor {
+    lines: Vec<Line>,
+    trailing_newline: bool,
+}
+
+/// A zone and its records as listed in an inventory file, with the 1-based
+/// line number of each.
+#[derive(Clone, Debug)]
+pub struct LocatedZone {
+    pub zone: String,
+    pub line: usize,
+    pub records: Vec<(InventoryRecord, usize)>,
+}
+
+#[derive(Clone, Debug)]
+enum Line {
+    /// Comments, blank lines and document markers.
+    Trivia(String),
+    /// A zone, e.g. `imbleau.com: # comment`
+    Zone(ZoneLine),
+    /// A record belonging to the closest preceding zone, e.g. `- record`
+    Record(RecordLine),
+}
+
+#[derive(Clone, Debug)]
+struct ZoneLine {
+    /// The original line, if unmodified.
+    raw: Option<String>,
+    /// The zone identifier, unquoted.
+    key: String,
+    /// The zone identifier, as written.
+    key_text: String,
+    /// Records written in flow style on the same line.
+    inline: Option<Vec<InventoryRecord>>,
+    comment: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct RecordLine {
+    /// The original lines, if unmodified.
+    raw: Option<String>,
+    indent: String,
+    record: InventoryRecord,
+    /// The record, as written.
+    value_text: String,
+    comment: Option<String>,
+}
+
+impl YamlEditor {
+    /// Parse an inventory file.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text_lines = text.lines().collect::<Vec<&str>>();
+        let mut lines = vec![];
+        let mut in_zone = false;
+        let mut idx = 0;
+        while idx < text_lines.len() {
+            let line = text_lines[idx];
+            let line_no = idx + 1;
+            idx += 1;
+            let trimmed = line.trim_start();
+            if trimmed.is_empty()
+                || trimmed.starts_with('#')
+                || line == "---"
+                || line == "..."
+            {
+                lines.push(Line::Trivia(line.to_string()));
+            } else if let Some(item) = trimmed.strip_prefix('-') {
+                if !in_zone {
+                    bail!("line {line_no}: record outside of a zone");
+                }
+                if !item.is_empty() && !item.starts_with([' ', '\t']) {
+                    bail!("line {line_no}: unsupported sequence item");
+                }
+                let indent = &line[..line.len() - trimmed.len()];
+                let (value_text, comment) = split_comment(item);
+                let value_text = value_text.trim().to_string();
+                let mut raw = line.to_string();
+                let record = if find_colon(&value_text).is_some() {
+                    // A detailed record in block style, continued on the
+                    // following, further indented lines, including block
+                    // sequences such as its groups
+                    let mut mapping = format!("{indent}  {}", item.trim());
+                    while let Some(next) = text_lines.get(idx) {
+                        let next_trimmed = next.trim_start();
+                        let next_indent = next.len() - next_trimmed.len();
+                        if next_indent <= indent.len()
+                            || next_trimmed.is_empty()
+                            || next_trimmed.starts_with('#')
+                        {
+                            break;
+                        }
+                        mapping.push('\n');
+                        mapping.push_str(next);
+                        raw.push('\n');
+                        raw.push_str(next);
+                        idx += 1;
+                    }
+                    serde_yaml::from_str::<InventoryRecord>(&mapping)
+                        .with_context(|| {
+                            format!("line {line_no}: invalid record")
+                        })?
+                } else {
+                    serde_yaml::from_str::<serde_yaml::Value>(&value_text)
+                        .map_err(anyhow::Error::from)
+                        .and_then(value_to_record)
+                        .with_context(|| {
+                            format!("line {line_no}: invalid record")
+                        })?
+                };
+                lines.push(Line::Record(RecordLine {
+                    raw: Some(raw),
+                    indent: indent.to_string(),
+                    record,
+                    value_text,
+                    comment: comment.map(str::to_string),
+                }));
+            } else if line.starts_with([' ', '\t']) {
+                bail!("line {line_no}: unsupported indentation");
+            } else {
+                let (content, comment) = split_comment(line);
+                let colon = find_colon(content).with_context(|| {
+                    format!("line {line_no}: expected a zone")
+                })?;
+                let key_text = content[..colon].trim_end().to_string();
+                let key = parse_scalar(&key_text)
+                    .with_context(|| format!("line {line_no}"))?;
+                let rest = content[colon + 1..].trim();
+                let inline = match rest {
+                    "" | "~" | "null" => None,
+                    _ => Some(
+                        serde_yaml::from_str::<Option<Vec<serde_yaml::Value>>>(
+                            rest,
+                        )
+                        .with_context(|| {
+                            format!("line {line_no}: unsupported zone value")
+                        })?
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(value_to_record)
+                        .collect::<Result<Vec<InventoryRecord>>>()
+                        .with_context(|| format!("line {line_no}"))?,
+                    ),
+                };
+                lines.push(Line::Zone(ZoneLine {
+                    raw: Some(line.to_string()),
+                    key,
+                    key_text,
+                    inline,
+                    comment: comment.map(str::to_string),
+                }));
+                in_zone = true;
+            }
+        }
+        Ok(Self {
+            lines,
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+        })
+    }
+
+    /// Return the zones and records in the document, in order.
+    fn records(&self) -> Vec<(String, Vec<InventoryRecord>)> {
+        let mut entries: Vec<(String, Vec<InventoryRecord>)> = vec![];
+        for line in self.lines.iter() {
+            match line {
+                Line::Zone(zone) => entries.push((
+                    zone.key.clone(),
+                    zone.inline.clone().unwrap_or_default(),
+                )),
+                Line::Record(record) => {
+                    if let Some((_, records)) = entries.last_mut() {
+                        records.push(record.record.clone());
+                    }
+                }
+                Line::Trivia(_) => {}
+            }
+        }
+        entries
+    }
+
+    /// Return the zones and records as listed in the document, including
+    /// duplicates, with their line numbers. Lines are only accurate before
+    /// the document is edited.
+    pub fn located(&self) -> Vec<LocatedZone> {
+        let mut zones: Vec<LocatedZone> = vec![];
+        let mut line_no = 1;
+        for line in self.lines.iter() {
+            match line {
+                Line::Zone(zone) => zones.push(LocatedZone {
+                    zone: zone.key.clone(),
+                    line: line_no,
+                    records: zone
+                        .inline
+                        .iter()
+                        .flatten()
+                        .map(|record| (record.clone(), line_no))
+                        .collect(),
+                }),
+                Line::Record(record) => {
+                    if let Some(zone) = zones.last_mut() {
+                        zone.records.push((record.record.clone(), line_no));
+                    }
+                }
+                Line::Trivia(_) => {}
+            }
+            line_no += line.span();
+        }
+        zones
+    }
+
+    /// Edit the document to match the inventory data, leaving unchanged
+    /// entries, comments and ordering in place.
+    pub fn apply(&mut self, data: &InventoryData) {
+        let wanted = data.records();
+        let wanted_in = |zone: &str| {
+            wanted
+                .iter()
+                .find(|(key, _)| key == zone)
+                .map(|(_, records)| records.as_slice())
+        };
+
+        // Pair the entries of each zone with the records they become
+        let paired = self
+            .records()
+            .into_iter()
+            .map(|(zone, listed)| {
+                let pairs =
+                    pair_entries(&listed, wanted_in(&zone).unwrap_or_default());
+                (zone, pairs)
+            })
+            .collect::<Vec<(String, Vec<Option<usize>>)>>();
+
+        // Remove zones and records which are no longer wanted, and update
+        // records which changed
+        let mut zones = paired.iter();
+        let mut current = None;
+        self.lines.retain_mut(|line| match line {
+            Line::Zone(zone) => {
+                let pairs = zones.next().map(|(_, pairs)| pairs.iter());
+                current = wanted_in(&zone.key).zip(pairs);
+                if let (Some((records, pairs)), Some(inline)) =
+                    (current.as_mut(), zone.inline.as_mut())
+                {
+                    let before = inline.clone();
+                    *inline = before
+                        .iter()
+                        .zip(pairs.by_ref())
+                        .filter_map(|(_, pair)| {
+                            pair.map(|i| records[i].clone())
+                        })
+                        .collect();
+                    if *inline != before {
+                        zone.raw = None;
+                    }
+                }
+                current.is_some()
+            }
+            Line::Record(line) => {
+                let wanted = current.as_mut().and_then(|(records, pairs)| {
+                    pairs.next().copied().flatten().map(|i| &records[i])
+                });
+                if let Some(record) = wanted {
+                    if *record != line.record {
+                        line.set_record(record.clone());
+                    }
+                }
+                wanted.is_some()
+            }
+            Line::Trivia(_) => true,
+        });
+
+        // Add missing zones and records
+        for (zone, records) in wanted.iter() {
+            let listed = paired
+                .iter()
+                .filter(|(key, _)| key == zone)
+                .map(|(_, pairs)| pairs)
+                .collect::<Vec<&Vec<Option<usize>>>>();
+            if listed.is_empty() {
+                self.push_zone(zone);
+            }
+            for (idx, record) in records.iter().enumerate() {
+                let claimed =
+                    listed.iter().any(|pairs| pairs.contains(&Some(idx)));
+                if !claimed {
+                    self.push_record(zone, record);
+                }
+            }
+        }
+    }
+
+    /// Append a new, empty zone to the end of the document.
+    fn push_zone(&mut self, zone: &str) {
+        self.lines.push(Line::Zone(ZoneLine {
+            raw: None,
+            key: zone.to_string(),
+            key_text: quote(zone),
+            inline: None,
+            comment: None,
+        }));
+    }
+
+    /// Append a record after the last record of a zone.
+    fn push_record(&mut self, zone: &str, record: &InventoryRecord) {
+        let Some(start) = self
+            .lines
+            .iter()
+            .position(|line| matches!(line, Line::Zone(z) if z.key == zone))
+        else {
+            return;
+        };
+        // Records in flow style are converted to block style
+        if let Line::Zone(zone_line) = &mut self.lines[start] {
+            if let Some(inline) = zone_line.inline.take() {
+                zone_line.raw = None;
+                for (offset, record) in inline.iter().enumerate() {
+                    self.lines.insert(
+                        start + offset + 1,
+                        Line::Record(RecordLine::new("  ", record.clone())),
+                    );
+                }
+            }
+        }
+        let mut insert_at = start + 1;
+        let mut indent = "  ".to_string();
+        for (idx, line) in self.lines.iter().enumerate().skip(start + 1) {
+            match line {
+                Line::Zone(_) => break,
+                Line::Record(r) => {
+                    insert_at = idx + 1;
+                    indent = r.indent.clone();
+                }
+                Line::Trivia(_) => {}
+            }
+        }
+        self.lines.insert(
+            insert_at,
+            Line::Record(RecordLine::new(indent, record.clone())),
+        );
+    }
+
+    /// Annotate each zone and record with a comment containing its alias,
+    /// i.e. the name for an id, or the id for a name. Existing alias comments
+    /// are refreshed, while other user comments are kept.
+    pub fn annotate(
+        &mut self,
+        zones: &[Zone],
+        records: &[Record],
+    ) -> Vec<String> {
+        let mut unresolved = vec![];
+        let mut zone: Option<&Zone> = None;
+        for line in self.lines.iter_mut() {
+            match line {
+                Line::Zone(zone_line) => {
+                    zone = zones.iter().find(|z| {
+                        z.id == zone_line.key || z.name == zone_line.key
+                    });
+                    match zone {
+                        Some(z) => set_alias(
+                            &mut zone_line.comment,
+                            &mut zone_line.raw,
+                            if zone_line.key == z.id {
+                                &z.name
+                            } else {
+                                &z.id
+                            },
+                        ),
+                        None => unresolved.push(zone_line.key.clone()),
+                    }
+                }
+                Line::Record(record_line) => {
+                    let listed = &record_line.record;
+                    match resolve_alias(listed, zone, records) {
+                        Some(Some(alias)) => set_alias(
+                            &mut record_line.comment,
+                            &mut record_line.raw,
+                            &alias,
+                        ),
+                        Some(None) => {}
+                        None => unresolved.push(listed.key().to_string()),
+                    }
+                }
+                Line::Trivia(_) => {}
+            }
+        }
+        unresolved
+    }
+}
+
+impl std::fmt::Display for YamlEditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, line) in self.lines.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            match line {
+                Line::Trivia(text) => write!(f, "{text}")?,
+                Line::Zone(zone) => match zone.raw.as_ref() {
+                    Some(raw) => write!(f, "{raw}")?,
+                    None => {
+                        write!(f, "{}:", zone.key_text)?;
+                        if let Some(inline) = zone.inline.as_ref() {
+                            let values = inline
+                                .iter()
+                                .map(render)
+                                .collect::<Vec<String>>();
+                            write!(f, " [{}]", values.join(", "))?;
+                        }
+                        if let Some(comment) = zone.comment.as_ref() {
+                            write!(f, " #{comment}")?;
+                        }
+                    }
+                },
+                Line::Record(record) => match record.raw.as_ref() {
+                    Some(raw) => write!(f, "{raw}")?,
+                    None => {
+                        write!(f, "{}- {}", record.indent, record.value_text)?;
+                        if let Some(comment) = record.comment.as_ref() {
+                            write!(f, " #{comment}")?;
+                        }
+                    }
+                },
+            }
+        }
+        if self.trailing_newline && !self.lines.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Line {
+    /// The number of lines in the document this line spans.
+    fn span(&self) -> usize {
+        match self {
+            Line::Record(RecordLine { raw: Some(raw), .. }) => {
+                raw.lines().count().max(1)
+            }
+            _ => 1,
+        }
+    }
+}
+
+impl RecordLine {
+    fn new(indent: impl Into<String>, record: InventoryRecord) -> Self {
+        Self {
+            raw: None,
+            indent: indent.into(),
+            value_text: render(&record),
+            record,
+            comment: None,
+        }
+    }
+
+    /// Replace the record, keeping its comment.
+    fn set_record(&mut self, record: InventoryRecord) {
+        self.value_text = render(&record);
+        self.record = record;
+        self.raw = None;
+    }
+}
+
+/// Set an alias comment, unless a user comment is present.
+fn set_alias(
+    comment: &mut Option<String>,
+    raw: &mut Option<String>,
+    alias: &str,
+) {
+    let alias = format!(" '{alias}'");
+    let replace = match comment.as_deref() {
+        None => true,
+        Some(existing) => is_alias(existing) && existing != alias,
+    };
+    if replace {
+        comment.replace(alias);
+        raw.take();
+    }
+}
+
+/// Resolve a listed record to its alias: the name for an id, the id for a
+/// name, or the names a pattern currently matches. Detailed records already
+/// list their name, so have no alias. Returns `None` if the record can not
+/// be resolved.
+fn resolve_alias(
+    listed: &InventoryRecord,
+    zone: Option<&Zone>,
+    records: &[Record],
+) -> Option<Option<String>> {
+    let zone = zone?;
+    let mut in_zone = records.iter().filter(|r| r.zone_id == zone.id);
+    if let Ok(Some(pattern)) = listed.pattern() {
+        let mut names = in_zone
+            .filter(|r| pattern.is_match(&r.name))
+            .map(|r| r.name.as_str())
+            .collect::<Vec<&str>>();
+        names.dedup();
+        return (!names.is_empty()).then(|| Some(names.join(", ")));
+    }
+    let record =
+        in_zone.find(|r| listed.refers_to(r) || listed.relinks_to(r))?;
+    match listed {
+        InventoryRecord::Plain(key) if *key == record.id => {
+            Some(Some(record.name.clone()))
+        }
+        InventoryRecord::Plain(_) => Some(Some(record.id.clone())),
+        _ => Some(None),
+    }
+}
+
+/// Pair each listed entry with the index of the wanted record it becomes, if
+/// any, claiming each wanted record once. The same record is preferred, then
+/// one with the same name and type, e.g. re-linked to a new id, then one with
+/// the same name, e.g. a plain entry which was linked.
+fn pair_entries(
+    listed: &[InventoryRecord],
+    wanted: &[InventoryRecord],
+) -> Vec<Option<usize>> {
+    let passes: [fn(&InventoryRecord, &InventoryRecord) -> bool; 3] = [
+        |a, b| a == b,
+        |a, b| {
+            std::mem::discriminant(a) == std::mem::discriminant(b)
+                && a.key() == b.key()
+                && a.record_type() == b.record_type()
+        },
+        |a, b| a.key() == b.key() && a.is_pattern() == b.is_pattern(),
+    ];
+    let mut pairs = vec![None; listed.len()];
+    let mut claimed = vec![false; wanted.len()];
+    for same in passes {
+        for (listed, pair) in listed.iter().zip(pairs.iter_mut()) {
+            if pair.is_some() {
+                continue;
+            }
+            let found = wanted
+                .iter()
+                .enumerate()
+                .position(|(idx, w)| !claimed[idx] && same(listed, w));
+            if let Some(idx) = found {
+                claimed[idx] = true;
+                *pair = Some(idx);
+            }
+        }
+    }
+    pairs
+}
+
+/// Returns whether a comment was generated as an alias, e.g. `# 'name'`.
+fn is_alias(comment: &str) -> bool {
+    let comment = comment.trim();
+    comment.len() >= 2
+        && comment.starts_with('\'')
+        && comment.ends_with('\'')
+        && !comment[1..comment.len() - 1].contains('\'')
+}
+
+/// Split a line into its content and comment (excluding `#`).
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quote: Option<char> = None;
+    let mut prev = ' ';
+    for (idx, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if prev == ' ' || prev == '\t' => {
+                return (&line[..idx], Some(&line[idx + 1..]));
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    (line, None)
+}
+
+/// Find the colon separating a mapping key from its value.
+fn find_colon(content: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let chars = content.char_indices().collect::<Vec<(usize, char)>>();
+    for (i, (idx, c)) in chars.iter().enumerate() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(*c),
+            (Some(q), c) if *c == q => quote = None,
+            (None, ':') => {
+                let next = chars.get(i + 1).map(|(_, c)| *c);
+                if next.is_none() || next == Some(' ') || next == Some('\t') {
+                    return Some(*idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse a YAML scalar, such as a plain or quoted string.
+fn parse_scalar(text: &str) -> Result<String> {
+    let value = serde_yaml::from_str::<serde_yaml::Value>(text)
+        .with_context(|| format!("invalid value '{text}'"))?;
+    scalar_to_string(value)
+}
+
+fn scalar_to_string(value: serde_yaml::Value) -> Result<String> {
+    match value {
+        serde_yaml::Value::String(s) => Ok(s),
+        serde_yaml::Value::Number(n) => Ok(n.to_string()),
+        serde_yaml::Value::Bool(b) => Ok(b.to_string()),
+        other => bail!("unsupported value '{other:?}'"),
+    }
+}
+
+/// Convert a YAML scalar or mapping to a record.
+fn value_to_record(value: serde_yaml::Value) -> Result<InventoryRecord> {
+    match value {
+        serde_yaml::Value::Mapping(_) => {
+            serde_yaml::from_value(value).context("invalid detailed record")
+        }
+        value => scalar_to_string(value).map(InventoryRecord::Plain),
+    }
+}
+
+/// Format a record in YAML, as a scalar or a flow mapping.
+fn render(record: &InventoryRecord) -> String {
+    let mut fields = vec![];
+    match record {
+        InventoryRecord::Plain(key) => return quote(key),
+        InventoryRecord::Detailed {
+            name,
+            id,
+            record_type,
+            ..
+        } => {
+            fields.push(format!("name: {}", quote(name)));
+            if let Some(id) = id {
+                fields.push(format!("id: {}", quote(id)));
+            }
+            if let Some(record_type) = record_type {
+                fields.push(format!("type: {}", quote(record_type)));
+            }
+        }
+        InventoryRecord::Glob { glob, .. } => {
+            fields.push(format!("glob: {}", quote(glob)))
+        }
+        InventoryRecord::Regex { regex, .. } => {
+            fields.push(format!("regex: {}", quote(regex)))
+        }
+    }
+    if !record.groups().is_empty() {
+        let groups = record.groups().iter().map(|g| quote(g));
+        fields.push(format!(
+            "groups: [{}]",
+            groups.collect::<Vec<_>>().join(", ")
+        ));
+    }
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Format a string as a YAML scalar, quoting if necessary.
+fn quote(value: &str) -> String {
+    serde_yaml::to_string(value)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_else(|_| format!("'{}'", value.replace('\'', "''")))
+}
+
+/// A structure-aware editor for TOML inventory files, which preserves user
+/// comments, whitespace and ordering while editing.
+///
+/// Inventory files are a table of zones to arrays of records. Anything else
+/// is rejected, so callers can fall back to rewriting the file.
+#[derive(Clone, Debug)]
+pub struct TomlEditor {
+    document: toml_edit::Document,
+}
+
+impl TomlEditor {
+    /// Parse an inventory file.
+    pub fn parse(text: &str) -> Result<Self> {
+        let document = text
+            .parse::<toml_edit::Document>()
+            .context("parsing TOML inventory")?;
+        for (key, item) in document.iter() {
+            let Some(array) = item.as_array() else {
+                bail!("unsupported inventory structure for zone '{key}'");
+            };
+            if array.iter().any(|value| toml_record(value).is_none()) {
+                bail!("unsupported record value in zone '{key}'");
+            }
+        }
+        Ok(Self { document })
+    }
+
+    /// Return the zones and records in the document, in order, including
+    /// duplicates.
+    pub fn entries(&self) -> Vec<(String, Vec<InventoryRecord>)> {
+        self.document
+            .iter()
+            .map(|(key, item)| {
+                let records = item
+                    .as_array()
+                    .map(|array| array.iter().filter_map(toml_record).collect())
+                    .unwrap_or_default();
+                (key.to_string(), records)
+            })
+            .collect()
+    }
+
+    /// Edit the document to match the inventory data, leaving unchanged
+    /// entries, comments and ordering in place.
+    pub fn apply(&mut self, data: &InventoryData) {
+        let wanted = data.records();
+
+        // Remove zones which are no longer wanted, keeping the comments above
+        let mut comments = String::new();
+        let keys = self
+            .document
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .collect::<Vec<String>>();
+        let table = self.document.as_table_mut();
+        for key in keys {
+            let prefix = table
+                .key_decor(&key)
+                .and_then(|decor| decor.prefix())
+                .and_then(|raw| raw.as_str())
+                .unwrap_or_default()
+                .to_string();
+            if wanted.iter().any(|(zone, _)| *zone == key) {
+                if !comments.is_empty() {
+                    if let Some(decor) = table.key_decor_mut(&key) {
+                        decor.set_prefix(format!("{comments}{prefix}"));
+                    }
+                    comments.clear();
+                }
+            } else {
+                comments.push_str(prefix.trim_matches(' '));
+                table.remove(&key);
+            }
+        }
+        if !comments.is_empty() {
+            let trailing =
+                self.document.trailing().as_str().unwrap_or_default();
+            let trailing = format!("{comments}{trailing}");
+            self.document.set_trailing(trailing);
+        }
+        let table = self.document.as_table_mut();
+
+        for (zone, records) in wanted {
+            let item = table
+                .entry(&zone)
+                .or_insert(toml_edit::value(toml_edit::Array::new()));
+            let Some(array) = item.as_array_mut() else {
+                continue;
+            };
+            // Remove records which are no longer wanted, and update records
+            // which changed
+            let listed =
+                array.iter().filter_map(toml_record).collect::<Vec<_>>();
+            let pairs = pair_entries(&listed, &records);
+            let unclaimed = (0..records.len())
+                .filter(|idx| !pairs.contains(&Some(*idx)))
+                .collect::<Vec<usize>>();
+            let mut pairs = pairs.into_iter();
+            let mut idx = 0;
+            while idx < array.len() {
+                let listed = array.get(idx).and_then(toml_record);
+                let wanted = match listed {
+                    Some(_) => pairs.next().flatten().map(|i| &records[i]),
+                    None => None,
+                };
+                match (listed, wanted) {
+                    (Some(listed), Some(wanted)) => {
+                        if listed != *wanted {
+                            replace_element(array, idx, wanted);
+                        }
+                        idx += 1;
+                    }
+                    _ => remove_element(array, idx),
+                }
+            }
+            // Add missing records
+            for idx in unclaimed {
+                push_element(array, &records[idx]);
+            }
+        }
+    }
+
+    /// Annotate each zone and record with a comment containing its alias,
+    /// i.e. the name for an id, or the id for a name. Existing alias comments
+    /// are refreshed, while other user comments are kept.
+    pub fn annotate(
+        &mut self,
+        zones: &[Zone],
+        records: &[Record],
+    ) -> Vec<String> {
+        let mut unresolved = vec![];
+        for (key, item) in self.document.as_table_mut().iter_mut() {
+            let Some(value) = item.as_value_mut() else {
+                continue;
+            };
+            let zone = zones
+                .iter()
+                .find(|z| z.id == key.get() || z.name == key.get());
+            if let Some(z) = zone {
+                let alias = if key.get() == z.id { &z.name } else { &z.id };
+                // The zone alias follows the array, e.g. `zone = [] # 'alias'`
+                let suffix = value.decor().suffix().and_then(|s| s.as_str());
+                let suffix = suffix.unwrap_or_default().to_string();
+                if let Some(suffix) = set_slot_alias(&suffix, alias) {
+                    value.decor_mut().set_suffix(suffix);
+                }
+            } else {
+                unresolved.push(key.get().to_string());
+            }
+            let Some(array) = value.as_array_mut() else {
+                continue;
+            };
+            let mut aliases = vec![];
+            for value in array.iter() {
+                let Some(listed) = toml_record(value) else {
+                    aliases.push(None);
+                    continue;
+                };
+                match resolve_alias(&listed, zone, records) {
+                    Some(alias) => aliases.push(alias),
+                    None => {
+                        unresolved.push(listed.key().to_string());
+                        aliases.push(None);
+                    }
+                }
+            }
+            if aliases.iter().any(Option::is_some) && !is_multiline(array) {
+                to_multiline(array);
+            }
+            for (idx, alias) in aliases.into_iter().enumerate() {
+                let Some(alias) = alias else {
+                    continue;
+                };
+                if let Some(slot) =
+                    set_slot_alias(&comment_slot(array, idx), &alias)
+                {
+                    set_comment_slot(array, idx, slot);
+                }
+            }
+        }
+        unresolved
+    }
+}
+
+impl std::fmt::Display for TomlEditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+/// Returns whether an array is written over multiple lines.
+fn is_multiline(array: &toml_edit::Array) -> bool {
+    let has_newline = |raw: Option<&toml_edit::RawString>| {
+        raw.and_then(|raw| raw.as_str())
+            .is_some_and(|raw| raw.contains('\n'))
+    };
+    has_newline(Some(array.trailing()))
+        || array
+            .iter()
+            .any(|value| has_newline(value.decor().prefix()))
+}
+
+/// Write an array with one element per line.
+fn to_multiline(array: &mut toml_edit::Array) {
+    for value in array.iter_mut() {
+        value.decor_mut().set_prefix("\n  ");
+        value.decor_mut().set_suffix("");
+    }
+    array.set_trailing_comma(true);
+    array.set_trailing("\n");
+}
+
+/// Return the whitespace and comments following an element of a multi-line
+/// array, i.e. the prefix of the next element, or the array's trailing text.
+fn comment_slot(array: &toml_edit::Array, idx: usize) -> String {
+    let raw = match array.get(idx + 1) {
+        Some(next) => next.decor().prefix(),
+        None => Some(array.trailing()),
+    };
+    raw.and_then(|raw| raw.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Replace the whitespace and comments following an element.
+fn set_comment_slot(array: &mut toml_edit::Array, idx: usize, slot: String) {
+    match array.get_mut(idx + 1) {
+        Some(next) => next.decor_mut().set_prefix(slot),
+        None => array.set_trailing(slot),
+    }
+}
+
+/// Return the text with an alias comment before its first line break, unless
+/// a user comment is present or the alias is up to date.
+fn set_slot_alias(slot: &str, alias: &str) -> Option<String> {
+    let (line, rest) = match slot.find('\n') {
+        Some(idx) => slot.split_at(idx),
+        None => (slot, ""),
+    };
+    let (_, comment) = split_comment(line);
+    let alias = format!(" '{alias}'");
+    let replace = match comment {
+        None => true,
+        Some(existing) => is_alias(existing) && existing != alias,
+    };
+    replace.then(|| format!(" #{alias}{rest}"))
+}
+
+/// Remove an element from an array, keeping the comment of the element
+/// before it.
+fn remove_element(array: &mut toml_edit::Array, idx: usize) {
+    if !is_multiline(array) {
+        array.remove(idx);
+        array.fmt();
+        return;
+    }
+    // The prefix holds the previous element's comment, then indentation
+    let prefix = array
+        .get(idx)
+        .and_then(|v| v.decor().prefix())
+        .and_then(|raw| raw.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let previous = match prefix.find('\n') {
+        Some(newline) => &prefix[..newline],
+        None => "",
+    };
+    // The slot after the element holds its comment, then indentation
+    let slot = comment_slot(array, idx);
+    let rest = match slot.find('\n') {
+        Some(newline) => &slot[newline..],
+        None => "",
+    };
+    array.remove(idx);
+    let slot = format!("{previous}{rest}");
+    match array.get_mut(idx) {
+        Some(next) => next.decor_mut().set_prefix(slot),
+        None => array.set_trailing(slot),
+    }
+}
+
+/// Read a record from a TOML string or inline table.
+fn toml_record(value: &toml_edit::Value) -> Option<InventoryRecord> {
+    if let Some(key) = value.as_str() {
+        return Some(InventoryRecord::Plain(key.to_string()));
+    }
+    let table = value.as_inline_table()?;
+    let field = |name: &str| table.get(name)?.as_str().map(str::to_string);
+    let groups = match table.get("groups") {
+        Some(groups) => groups
+            .as_array()?
+            .iter()
+            .map(|g| g.as_str().map(str::to_string))
+            .collect::<Option<Vec<String>>>()?,
+        None => vec![],
+    };
+    if let Some(glob) = field("glob") {
+        return Some(InventoryRecord::Glob { glob, groups });
+    }
+    if let Some(regex) = field("regex") {
+        return Some(InventoryRecord::Regex { regex, groups });
+    }
+    Some(InventoryRecord::Detailed {
+        name: field("name")?,
+        id: field("id"),
+        record_type: field("type"),
+        groups,
+    })
+}
+
+/// Convert a record to a TOML string or inline table.
+fn toml_value(record: &InventoryRecord) -> toml_edit::Value {
+    let mut table = toml_edit::InlineTable::new();
+    match record {
+        InventoryRecord::Plain(key) => {
+            return toml_edit::Value::from(key.as_str())
+        }
+        InventoryRecord::Detailed {
+            name,
+            id,
+            record_type,
+            ..
+        } => {
+            table.insert("name", name.as_str().into());
+            if let Some(id) = id {
+                table.insert("id", id.as_str().into());
+            }
+            if let Some(record_type) = record_type {
+                table.insert("type", record_type.as_str().into());
+            }
+        }
+        InventoryRecord::Glob { glob, .. } => {
+            table.insert("glob", glob.as_str().into());
+        }
+        InventoryRecord::Regex { regex, .. } => {
+            table.insert("regex", regex.as_str().into());
+        }
+    }
+    if !record.groups().is_empty() {
+        let groups = record.groups().iter().map(String::as_str);
+        table.insert("groups", toml_edit::Array::from_iter(groups).into());
+    }
+    table.fmt();
+    toml_edit::Value::InlineTable(table)
+}
+
+/// Replace an element of an array, keeping its whitespace and comments.
+fn replace_element(
+    array: &mut toml_edit::Array,
+    idx: usize,
+    record: &InventoryRecord,
+) {
+    let mut value = toml_value(record);
+    if let Some(old) = array.get(idx) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    array.replace_formatted(idx, value);
+}
+
+/// Append an element to an array, matching the style of the elements before
+/// it.
+fn push_element(array: &mut toml_edit::Array, record: &InventoryRecord) {
+    if !is_multiline(array) {
+        array.push(toml_value(record));
+        return;
+    }
+    let indent = array
+        .iter()
+        .last()
+        .and_then(|v| v.decor().prefix())
+        .and_then(|raw| raw.as_str())
+        .and_then(|prefix| prefix.rfind('\n').map(|idx| &prefix[idx..]))
+        .unwrap_or("\n  ")
+        .to_string();
+    // The trailing comment of the last element moves before the new one
+    let trailing = array.trailing().as_str().unwrap_or_default().to_string();
+    let (comment, rest) = match trailing.find('\n') {
+        Some(idx) => trailing.split_at(idx),
+        None => (trailing.as_str(), "\n"),
+    };
+    let mut element = toml_value(record);
+    element.decor_mut().set_prefix(format!("{comment}{indent}"));
+    element.decor_mut().set_suffix("");
+    array.push_formatted(element);
+    array.set_trailing(rest.to_string());
+    array.set_trailing_comma(true);
+}
+
+/// A structure-aware editor for inventory files in a format which supports
+/// comments.
+#[derive(Clone, Debug)]
+pub enum InventoryEditor {
+    Yaml(YamlEditor),
+    Toml(Box<TomlEditor>),
+}
+
+impl InventoryEditor {
+    /// Parse an inventory file in the given format.
+    pub fn parse(text: &str, format: InventoryFormat) -> Result<Self> {
+        match format {
+            InventoryFormat::Yaml => Ok(Self::Yaml(YamlEditor::parse(text)?)),
+            InventoryFormat::Toml => {
+                Ok(Self::Toml(Box::new(TomlEditor::parse(text)?)))
+            }
+            InventoryFormat::Json => {
+                bail!("JSON inventories can not be edited")
+            }
+        }
+    }
+
+    /// Edit the document to match the inventory data.
+    pub fn apply(&mut self, data: &InventoryData) {
+        match self {
+            Self::Yaml(editor) => editor.apply(data),
+            Self::Toml(editor) => editor.apply(data),
+        }
+    }
+
+    /// Annotate each zone and record with a comment containing its alias.
+    /// Returns the entries which could not be resolved.
+    pub fn annotate(
+        &mut self,
+        zones: &[Zone],
+        records: &[Record],
+    ) -> Vec<String> {
+        match self {
+            Self::Yaml(editor) => editor.annotate(zones, records),
+            Self::Toml(editor) => editor.annotate(zones, records),
+        }
+    }
+}
+
+impl std::fmt::Display for InventoryEditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml(editor) => write!(f, "{editor}"),
+            Self::Toml(editor) => write!(f, "{editor}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Result<InventoryData> {
+        InventoryFormat::Yaml.decode(text.as_bytes())
+    }
+
+    fn edit(text: &str, wanted: &str) -> Result<String> {
+        let mut editor = YamlEditor::parse(text)?;
+        editor.apply(&yaml(wanted)?);
+        Ok(editor.to_string())
+    }
+
+    #[test]
+    fn yaml_keeps_comments_when_removing_and_adding() -> Result<()> {
+        let text = "\
+# Managed by cddns
+imbleau.com: # home
+  # The website
+  - www.imbleau.com # web
+  - old.imbleau.com
+
+# Other zones
+imbleau.dev:
+  - imbleau.dev
+";
+        let wanted = "\
+imbleau.com: [www.imbleau.com, new.imbleau.com]
+imbleau.dev: [imbleau.dev]
+";
+        assert_eq!(
+            edit(text, wanted)?,
+            "\
+# Managed by cddns
+imbleau.com: # home
+  # The website
+  - www.imbleau.com # web
+  - new.imbleau.com
+
+# Other zones
+imbleau.dev:
+  - imbleau.dev
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_removes_zones_and_appends_new_zones() -> Result<()> {
+        let text = "\
+# Keep me
+imbleau.com:
+  - www.imbleau.com
+imbleau.dev:
+  - imbleau.dev
+";
+        let wanted = "\
+imbleau.com: [www.imbleau.com]
+example.com: [example.com]
+";
+        assert_eq!(
+            edit(text, wanted)?,
+            "\
+# Keep me
+imbleau.com:
+  - www.imbleau.com
+example.com:
+  - example.com
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_converts_flow_sequences_when_adding() -> Result<()> {
+        let text = "imbleau.com: [www.imbleau.com] # home\n";
+        let wanted = "imbleau.com: [www.imbleau.com, api.imbleau.com]\n";
+        assert_eq!(
+            edit(text, wanted)?,
+            "imbleau.com: # home\n  - www.imbleau.com\n  - api.imbleau.com\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_keeps_nested_block_records() -> Result<()> {
+        let text = "\
+imbleau.com:
+  - name: www.imbleau.com # web
+    id: '1234'
+    type: A
+  - api.imbleau.com
+";
+        let editor = YamlEditor::parse(text)?;
+        let located = editor.located();
+        assert_eq!(located.len(), 1);
+        assert_eq!(located[0].line, 1);
+        let lines = located[0]
+            .records
+            .iter()
+            .map(|(record, line)| (record.key().to_string(), *line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                ("www.imbleau.com".to_string(), 2),
+                ("api.imbleau.com".to_string(), 5)
+            ]
+        );
+
+        // Unchanged records are written as they were
+        assert_eq!(edit(text, text)?, text);
+
+        // Changed records are rewritten in flow style, in place
+        let wanted = "\
+imbleau.com:
+  - {name: www.imbleau.com, id: '5678', type: A}
+  - api.imbleau.com
+";
+        assert_eq!(
+            edit(text, wanted)?,
+            "\
+imbleau.com:
+  - {name: www.imbleau.com, id: '5678', type: A} # web
+  - api.imbleau.com
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_keeps_block_sequences_in_block_records() -> Result<()> {
+        let text = "\
+# home
+imbleau.com:
+  - name: www.imbleau.com
+    groups:
+      - web
+  - name: api.imbleau.com
+    groups:
+    - api
+    - web
+  - old.imbleau.com
+";
+        let editor = YamlEditor::parse(text)?;
+        let lines = editor.located()[0]
+            .records
+            .iter()
+            .map(|(record, line)| (record.groups().to_vec(), *line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                (vec!["web".to_string()], 3),
+                (vec!["api".to_string(), "web".to_string()], 6),
+                (vec![], 10),
+            ]
+        );
+
+        // Unchanged records are kept, along with the comments
+        let wanted = "\
+imbleau.com:
+  - {name: www.imbleau.com, groups: [web]}
+  - {name: api.imbleau.com, groups: [api, web]}
+";
+        assert_eq!(
+            edit(text, wanted)?,
+            "\
+# home
+imbleau.com:
+  - name: www.imbleau.com
+    groups:
+      - web
+  - name: api.imbleau.com
+    groups:
+    - api
+    - web
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_keeps_empty_zones_and_patterns() -> Result<()> {
+        let text = "\
+imbleau.com: ~
+imbleau.dev:
+  - {glob: '*.imbleau.dev', groups: [lab]}
+";
+        assert_eq!(edit(text, text)?, text);
+        Ok(())
+    }
+
+    #[test]
+    fn toml_keeps_comments_when_removing() -> Result<()> {
+        let text = "\
+# Managed by cddns
+\"imbleau.com\" = [
+    \"www.imbleau.com\", # web
+    \"old.imbleau.com\",
+]
+";
+        let mut editor = TomlEditor::parse(text)?;
+        editor.apply(&yaml("imbleau.com: [www.imbleau.com]\n")?);
+        assert_eq!(
+            editor.to_string(),
+            "\
+# Managed by cddns
+\"imbleau.com\" = [
+    \"www.imbleau.com\", # web
+]
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_updates_only_the_relinked_record() -> Result<()> {
+        let text = "\
+imbleau.com:
+  - {name: www.imbleau.com, id: '1', type: A} # v4
+  - {name: www.imbleau.com, id: '2', type: AAAA} # v6
+";
+        let mut data = yaml(text)?;
+        let relinked = InventoryRecord::Detailed {
+            name: "www.imbleau.com".to_string(),
+            id: Some("3".to_string()),
+            record_type: Some("AAAA".to_string()),
+            groups: vec![],
+        };
+        let previous = InventoryRecord::Detailed {
+            name: "www.imbleau.com".to_string(),
+            id: Some("2".to_string()),
+            record_type: Some("AAAA".to_string()),
+            groups: vec![],
+        };
+        assert!(data.replace("imbleau.com", &previous, relinked));
+        let mut editor = YamlEditor::parse(text)?;
+        editor.apply(&data);
+        assert_eq!(
+            editor.to_string(),
+            "\
+imbleau.com:
+  - {name: www.imbleau.com, id: '1', type: A} # v4
+  - {name: www.imbleau.com, id: '3', type: AAAA} # v6
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn toml_updates_only_the_relinked_record() -> Result<()> {
+        let text = "\
+\"imbleau.com\" = [
+    { name = \"www.imbleau.com\", id = \"1\", type = \"A\" }, # v4
+    { name = \"www.imbleau.com\", id = \"2\", type = \"AAAA\" }, # v6
+]
+";
+        let mut data = InventoryFormat::Toml.decode(text.as_bytes())?;
+        let relinked = InventoryRecord::Detailed {
+            name: "www.imbleau.com".to_string(),
+            id: Some("3".to_string()),
+            record_type: Some("AAAA".to_string()),
+            groups: vec![],
+        };
+        let previous = InventoryRecord::Detailed {
+            name: "www.imbleau.com".to_string(),
+            id: Some("2".to_string()),
+            record_type: Some("AAAA".to_string()),
+            groups: vec![],
+        };
+        assert!(data.replace("imbleau.com", &previous, relinked));
+        let mut editor = TomlEditor::parse(text)?;
+        editor.apply(&data);
+        let entries = editor.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].1.len(), 2);
+        assert_eq!(entries[0].1[0].id(), Some("1"));
+        assert_eq!(entries[0].1[0].record_type(), Some("A"));
+        assert_eq!(entries[0].1[1].id(), Some("3"));
+        assert_eq!(entries[0].1[1].record_type(), Some("AAAA"));
+        assert!(editor.to_string().contains("# v6"));
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_rejects_nested_zones() -> Result<()> {
+        let text = "imbleau.com:\n  sub:\n    - www.imbleau.com\n";
+        assert!(YamlEditor::parse(text).is_err());
+        assert!(YamlEditor::parse("- www.imbleau.com\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use crate::cloudflare::models::Record;
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// The TTL Cloudflare uses for records with an automatic TTL.
+const AUTOMATIC_TTL: u32 = 300;
+
+/// The formats managed records can be exported as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// RFC 1035 zone file lines.
+    Bind,
+    /// Terraform `cloudflare_record` resources.
+    Terraform,
+    /// octoDNS zone YAML.
+    Octodns,
+}
+
+impl ExportFormat {
+    /// Export records, grouped by zone.
+    pub fn export(&self, records: &[Record]) -> Result<String> {
+        let mut records = records.iter().collect::<Vec<&Record>>();
+        records.sort_by(|a, b| {
+            (&a.zone_name, &a.name, &a.record_type, &a.content).cmp(&(
+                &b.zone_name,
+                &b.name,
+                &b.record_type,
+                &b.content,
+            ))
+        });
+        let mut zones: IndexMap<&str, Vec<&Record>> = IndexMap::new();
+        for record in records {
+            zones.entry(&record.zone_name).or_default().push(record);
+        }
+        match self {
+            ExportFormat::Bind => Ok(bind(&zones)),
+            ExportFormat::Terraform => Ok(terraform(&zones)),
+            ExportFormat::Octodns => octodns(&zones),
+        }
+    }
+}
+
+/// Return the TTL in seconds, resolving automatic TTLs.
+fn ttl(record: &Record) -> u32 {
+    match record.ttl {
+        1 => AUTOMATIC_TTL,
+        ttl => ttl,
+    }
+}
+
+/// Return a record name relative to its zone, where the apex is `@`.
+fn relative_name(record: &Record) -> String {
+    if record.name == record.zone_name {
+        return "@".to_string();
+    }
+    match record.name.strip_suffix(&format!(".{}", record.zone_name)) {
+        Some(name) => name.to_string(),
+        // Fully qualify names outside of the zone
+        None => format!("{}.", record.name),
+    }
+}
+
+/// Format zones as RFC 1035 zone file lines, e.g.
+/// `www 300 IN A 203.0.113.7`
+fn bind(zones: &IndexMap<&str, Vec<&Record>>) -> String {
+    let mut out = String::new();
+    for (zone, records) in zones {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "$ORIGIN {zone}.");
+        for record in records {
+            let _ = writeln!(
+                out,
+                "{} {} IN {} {}",
+                relative_name(record),
+                ttl(record),
+                record.record_type,
+                record.content
+            );
+        }
+    }
+    out
+}
+
+/// Format zones as Terraform `cloudflare_record` resources.
+fn terraform(zones: &IndexMap<&str, Vec<&Record>>) -> String {
+    let mut out = String::new();
+    let mut labels = HashSet::new();
+    for record in zones.values().flatten() {
+        // Resource labels must be unique identifiers
+        let base = format!("{}_{}", record.name, record.record_type)
+            .replace('*', "wildcard")
+            .to_lowercase()
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c,
+                false => '_',
+            })
+            .collect::<String>();
+        let base = match base.starts_with(|c: char| c.is_ascii_digit()) {
+            true => format!("_{base}"),
+            false => base,
+        };
+        let mut label = base.clone();
+        let mut n = 1;
+        while !labels.insert(label.clone()) {
+            n += 1;
+            label = format!("{base}_{n}");
+        }
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "resource \"cloudflare_record\" \"{label}\" {{");
+        let _ = writeln!(out, "  zone_id = \"{}\"", record.zone_id);
+        let _ = writeln!(out, "  name    = \"{}\"", relative_name(record));
+        let _ = writeln!(out, "  type    = \"{}\"", record.record_type);
+        let _ = writeln!(out, "  content = \"{}\"", record.content);
+        let _ = writeln!(out, "  ttl     = {}", record.ttl);
+        let _ = writeln!(out, "  proxied = {}", record.proxied);
+        let _ = writeln!(out, "}}");
+    }
+    out
+}
+
+/// An octoDNS record.
+#[derive(Serialize)]
+struct OctodnsRecord {
+    #[serde(rename = "type")]
+    record_type: String,
+    ttl: u32,
+    values: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    octodns: Option<IndexMap<&'static str, IndexMap<&'static str, bool>>>,
+}
+
+/// Format zones as octoDNS YAML documents, one per zone, where apex records
+/// are listed under `''`.
+fn octodns(zones: &IndexMap<&str, Vec<&Record>>) -> Result<String> {
+    let mut out = String::new();
+    for (zone, records) in zones {
+        let mut names: IndexMap<String, Vec<OctodnsRecord>> = IndexMap::new();
+        for record in records {
+            let name = match relative_name(record).as_str() {
+                "@" => String::new(),
+                name => name.to_string(),
+            };
+            let entries = names.entry(name).or_default();
+            // Records with the same name and type share their values
+            match entries
+                .iter_mut()
+                .find(|e| e.record_type == record.record_type)
+            {
+                Some(entry) => entry.values.push(record.content.clone()),
+                None => entries.push(OctodnsRecord {
+                    record_type: record.record_type.clone(),
+                    ttl: ttl(record),
+                    values: vec![record.content.clone()],
+                    octodns: record.proxied.then(|| {
+                        IndexMap::from([(
+                            "cloudflare",
+                            IndexMap::from([("proxied", true)]),
+                        )])
+                    }),
+                }),
+            }
+        }
+        let _ = writeln!(out, "# {zone}.yaml");
+        let _ = writeln!(out, "---");
+        out.push_str(&crate::util::encoding::as_yaml(&names)?);
+    }
+    Ok(out)
+}
@@ -0,0 +1,615 @@
+use crate::cloudflare::models::{Record, Zone};
+use crate::inventory::models::InventoryData;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use tracing::warn;
+
+/// The configuration formats of other DDNS tools which can be imported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportFormat {
+    /// ddclient's `ddclient.conf`, using the Cloudflare protocol.
+    Ddclient,
+    /// inadyn's `inadyn.conf`, using a Cloudflare provider.
+    Inadyn,
+    /// The JSON config of the `cloudflare-ddns` Python script.
+    CloudflareDdns,
+}
+
+impl Display for ImportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportFormat::Ddclient => write!(f, "ddclient"),
+            ImportFormat::Inadyn => write!(f, "inadyn"),
+            ImportFormat::CloudflareDdns => write!(f, "cloudflare-ddns"),
+        }
+    }
+}
+
+impl ImportFormat {
+    /// Parse the hosts and token from another tool's configuration.
+    pub fn parse(&self, text: &str) -> Result<Import> {
+        let mut import = match self {
+            ImportFormat::Ddclient => parse_ddclient(text),
+            ImportFormat::Inadyn => parse_inadyn(text)?,
+            ImportFormat::CloudflareDdns => parse_cloudflare_ddns(text)?,
+        };
+        if import.hosts.is_empty() {
+            bail!("no Cloudflare hosts were found in the {self} config");
+        }
+        let mut seen = HashSet::new();
+        import.tokens.retain(|token| seen.insert(token.clone()));
+        if import.tokens.len() > 1 {
+            warn!(
+                "{} different tokens were found, only the first will be used",
+                import.tokens.len()
+            );
+        }
+        Ok(import)
+    }
+}
+
+/// A host to import, as written in another tool's configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportHost {
+    /// The zone id or name, if known.
+    pub zone: Option<String>,
+    /// The record name, which may be relative to the zone.
+    pub name: String,
+    /// The record type, if restricted to one.
+    pub record_type: Option<String>,
+}
+
+/// The hosts and API tokens found in another tool's configuration.
+#[derive(Clone, Debug, Default)]
+pub struct Import {
+    pub hosts: Vec<ImportHost>,
+    pub tokens: Vec<String>,
+}
+
+impl Import {
+    /// Return the first API token found, if any.
+    pub fn token(&self) -> Option<&str> {
+        self.tokens.first().map(String::as_str)
+    }
+
+    /// Resolve the hosts against Cloudflare zones and records, returning the
+    /// inventory data and the hosts which could not be resolved.
+    pub fn resolve(
+        &self,
+        zones: &[Zone],
+        records: &[Record],
+    ) -> (InventoryData, Vec<String>) {
+        let mut data = InventoryData(None);
+        let mut unresolved = vec![];
+        for host in self.hosts.iter() {
+            let zone = match host.zone {
+                Some(ref zone) => {
+                    zones.iter().find(|z| z.id == *zone || z.name == *zone)
+                }
+                // Use the most specific zone containing the host
+                None => zones
+                    .iter()
+                    .filter(|z| qualify(&host.name, &z.name) == host.name)
+                    .max_by_key(|z| z.name.len()),
+            };
+            let Some(zone) = zone else {
+                unresolved.push(host.name.clone());
+                continue;
+            };
+            let name = qualify(&host.name, &zone.name);
+            let matches = records
+                .iter()
+                .filter(|r| r.zone_id == zone.id && r.name == name)
+                .filter(|r| {
+                    host.record_type.is_none()
+                        || host.record_type.as_ref() == Some(&r.record_type)
+                })
+                .collect::<Vec<&Record>>();
+            if matches.is_empty() {
+                unresolved.push(name);
+            }
+            for record in matches {
+                data.insert(&zone.id, &record.id);
+            }
+        }
+        (data, unresolved)
+    }
+}
+
+/// Qualify a record name with its zone name, e.g. `www` or `@` in `example.com`
+/// becomes `www.example.com` or `example.com`.
+fn qualify(name: &str, zone: &str) -> String {
+    let name = name.trim_end_matches('.');
+    if name.is_empty() || name == "@" {
+        zone.to_string()
+    } else if name == zone || name.ends_with(&format!(".{zone}")) {
+        name.to_string()
+    } else {
+        format!("{name}.{zone}")
+    }
+}
+
+/// Strip a trailing `#` comment, outside of quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('#', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Remove matching quotes around a value.
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for q in ['"', '\''] {
+        if let Some(inner) =
+            value.strip_prefix(q).and_then(|v| v.strip_suffix(q))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Parse ddclient's `ddclient.conf`.
+///
+/// Lines are lists of `key=value` settings followed by host names, separated
+/// by commas or whitespace, and end in `\` to continue. Settings on a line
+/// without hosts apply to all following lines.
+fn parse_ddclient(text: &str) -> Import {
+    let mut import = Import::default();
+    let mut globals: HashMap<String, String> = HashMap::new();
+
+    // Join continued lines
+    let mut lines = vec![];
+    let mut current = String::new();
+    for line in text.lines() {
+        let line = strip_comment(line).trim_end();
+        match line.strip_suffix('\\') {
+            Some(line) => {
+                current.push_str(line);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(line);
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    lines.push(current);
+
+    for line in lines {
+        let mut locals = HashMap::new();
+        let mut hosts = vec![];
+        for word in line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|w| !w.is_empty())
+        {
+            match word.split_once('=') {
+                Some((key, value)) => {
+                    locals.insert(
+                        key.trim().to_lowercase(),
+                        unquote(value).to_string(),
+                    );
+                }
+                None => hosts.push(word.to_string()),
+            }
+        }
+        if hosts.is_empty() {
+            globals.extend(locals);
+            continue;
+        }
+        let mut settings = globals.clone();
+        settings.extend(locals);
+        if settings.get("protocol").map(String::as_str) != Some("cloudflare") {
+            continue;
+        }
+        match (settings.get("login"), settings.get("password")) {
+            (Some(login), Some(password)) if login == "token" => {
+                import.tokens.push(password.to_string())
+            }
+            (Some(_), Some(_)) => {
+                warn!("skipping a global API key, cddns needs an API token")
+            }
+            _ => {}
+        }
+        for host in hosts {
+            import.hosts.push(ImportHost {
+                zone: settings.get("zone").cloned(),
+                name: host,
+                record_type: None,
+            });
+        }
+    }
+    import
+}
+
+/// A value in an inadyn config.
+#[derive(Clone, Debug)]
+enum InadynValue {
+    Scalar(String),
+    List(Vec<String>),
+    Section(String, Vec<(String, InadynValue)>),
+}
+
+/// Parse inadyn's `inadyn.conf`.
+///
+/// Settings are `key = value` pairs, where values may be lists like
+/// `{ "a", "b" }`, and providers are sections like `provider cloudflare.com
+/// { ... }`. For Cloudflare, the username is the zone and the password is
+/// the API token.
+fn parse_inadyn(text: &str) -> Result<Import> {
+    // Tokenize
+    let mut tokens = vec![];
+    for line in text.lines() {
+        let mut chars = strip_comment(line).chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if matches!(c, '{' | '}' | '=' | ',') {
+                tokens.push(c.to_string());
+                chars.next();
+            } else if c == '"' || c == '\'' {
+                chars.next();
+                let word: String =
+                    chars.by_ref().take_while(|&n| n != c).collect();
+                // Mark strings so they are never mistaken for punctuation
+                tokens.push(format!("\0{word}"));
+            } else {
+                let mut word = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n.is_whitespace() || matches!(n, '{' | '}' | '=' | ',') {
+                        break;
+                    }
+                    word.push(n);
+                    chars.next();
+                }
+                tokens.push(word);
+            }
+        }
+    }
+    let mut tokens = tokens.into_iter().peekable();
+    let settings = inadyn_settings(&mut tokens)?;
+
+    let mut import = Import::default();
+    for (_, value) in settings {
+        let InadynValue::Section(name, settings) = value else {
+            continue;
+        };
+        if !name.contains("cloudflare") {
+            continue;
+        }
+        let mut zone = None;
+        let mut hosts = vec![];
+        for (key, value) in settings {
+            match (key.as_str(), value) {
+                ("username", InadynValue::Scalar(value)) => zone = Some(value),
+                ("password", InadynValue::Scalar(value)) => {
+                    import.tokens.push(value)
+                }
+                ("hostname", InadynValue::Scalar(value)) => hosts.push(value),
+                ("hostname", InadynValue::List(values)) => hosts.extend(values),
+                _ => {}
+            }
+        }
+        for host in hosts {
+            import.hosts.push(ImportHost {
+                zone: zone.clone(),
+                name: host,
+                record_type: None,
+            });
+        }
+    }
+    Ok(import)
+}
+
+/// Parse inadyn settings until the end of a section.
+fn inadyn_settings(
+    tokens: &mut std::iter::Peekable<impl Iterator<Item = String>>,
+) -> Result<Vec<(String, InadynValue)>> {
+    let unquoted = |token: String| token.trim_start_matches('\0').to_string();
+    let mut settings = vec![];
+    while let Some(key) = tokens.next() {
+        match key.as_str() {
+            "}" => break,
+            "," => continue,
+            _ => {}
+        }
+        let key = unquoted(key);
+        if tokens.next_if_eq("=").is_some() {
+            let token = tokens.next().context("missing inadyn value")?;
+            let value = match token.as_str() {
+                "{" => {
+                    let mut values = vec![];
+                    for token in tokens.by_ref() {
+                        match token.as_str() {
+                            "}" => break,
+                            "," => continue,
+                            _ => values.push(unquoted(token)),
+                        }
+                    }
+                    InadynValue::List(values)
+                }
+                _ => InadynValue::Scalar(unquoted(token)),
+            };
+            settings.push((key, value));
+        } else {
+            // A section, e.g. `provider default@cloudflare.com:1 { ... }`
+            let mut name = vec![];
+            for token in tokens.by_ref() {
+                match token.as_str() {
+                    "{" => break,
+                    _ => name.push(unquoted(token)),
+                }
+            }
+            let section = inadyn_settings(tokens)?;
+            settings.push((key, InadynValue::Section(name.join(" "), section)));
+        }
+    }
+    Ok(settings)
+}
+
+/// The JSON config of the `cloudflare-ddns` Python script.
+#[derive(Deserialize)]
+struct CloudflareDdnsConfig {
+    cloudflare: Vec<CloudflareDdnsZone>,
+    #[serde(default = "enabled")]
+    a: bool,
+    #[serde(default = "enabled")]
+    aaaa: bool,
+}
+
+#[derive(Deserialize)]
+struct CloudflareDdnsZone {
+    authentication: CloudflareDdnsAuthentication,
+    zone_id: String,
+    subdomains: Vec<CloudflareDdnsSubdomain>,
+}
+
+#[derive(Deserialize)]
+struct CloudflareDdnsAuthentication {
+    api_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CloudflareDdnsSubdomain {
+    Name(String),
+    Detailed { name: String },
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Parse the JSON config of the `cloudflare-ddns` Python script, where
+/// subdomains are relative to their zone.
+fn parse_cloudflare_ddns(text: &str) -> Result<Import> {
+    let config: CloudflareDdnsConfig = serde_json::from_str(text)
+        .context("reading cloudflare-ddns config as JSON data")?;
+    let record_type = match (config.a, config.aaaa) {
+        (true, false) => Some("A".to_string()),
+        (false, true) => Some("AAAA".to_string()),
+        _ => None,
+    };
+    let mut import = Import::default();
+    for zone in config.cloudflare {
+        match zone.authentication.api_token {
+            // Skip the placeholder from the script's example config
+            Some(token) if token != "api_token_here" => {
+                import.tokens.push(token)
+            }
+            _ => {}
+        }
+        for subdomain in zone.subdomains {
+            let name = match subdomain {
+                CloudflareDdnsSubdomain::Name(name) => name,
+                CloudflareDdnsSubdomain::Detailed { name } => name,
+            };
+            import.hosts.push(ImportHost {
+                zone: Some(zone.zone_id.clone()),
+                name,
+                record_type: record_type.clone(),
+            });
+        }
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::models::InventoryRecord;
+
+    fn host(zone: Option<&str>, name: &str) -> ImportHost {
+        ImportHost {
+            zone: zone.map(str::to_string),
+            name: name.to_string(),
+            record_type: None,
+        }
+    }
+
+    fn zone(id: &str, name: &str) -> Zone {
+        Zone {
+            id: id.to_string(),
+            name: name.to_string(),
+            permissions: vec![],
+            status: "active".to_string(),
+        }
+    }
+
+    fn record(id: &str, zone: &Zone, name: &str, record_type: &str) -> Record {
+        Record {
+            id: id.to_string(),
+            zone_id: zone.id.clone(),
+            zone_name: zone.name.clone(),
+            name: name.to_string(),
+            record_type: record_type.to_string(),
+            content: "1.2.3.4".to_string(),
+            locked: false,
+            ttl: 1,
+            proxied: false,
+        }
+    }
+
+    #[test]
+    fn parses_ddclient() -> Result<()> {
+        let text = "\
+# global settings
+protocol=cloudflare, \\
+zone=imbleau.com, \\
+login=token, \\
+password='secret'   # the token
+www.imbleau.com,imbleau.com
+
+protocol=dyndns2, login=me, password=other example.com
+protocol=cloudflare zone=example.com login=me@example.com password=key @
+";
+        let import = ImportFormat::Ddclient.parse(text)?;
+        assert_eq!(
+            import.hosts,
+            vec![
+                host(Some("imbleau.com"), "www.imbleau.com"),
+                host(Some("imbleau.com"), "imbleau.com"),
+                host(Some("example.com"), "@"),
+            ]
+        );
+        assert_eq!(import.tokens, vec!["secret"]);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_inadyn() -> Result<()> {
+        let text = r#"
+period = 300
+provider default@dyndns.org {
+    username = me
+    password = other
+    hostname = example.dyndns.org
+}
+provider cloudflare.com:1 {
+    username = imbleau.com
+    password = "secret # not a comment"
+    hostname = { "www.imbleau.com", "imbleau.com" }
+}
+"#;
+        let import = ImportFormat::Inadyn.parse(text)?;
+        assert_eq!(
+            import.hosts,
+            vec![
+                host(Some("imbleau.com"), "www.imbleau.com"),
+                host(Some("imbleau.com"), "imbleau.com"),
+            ]
+        );
+        assert_eq!(import.token(), Some("secret # not a comment"));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_cloudflare_ddns() -> Result<()> {
+        let text = r#"{
+  "cloudflare": [
+    {
+      "authentication": { "api_token": "api_token_here" },
+      "zone_id": "zone1",
+      "subdomains": ["", { "name": "www", "proxied": true }]
+    },
+    {
+      "authentication": { "api_token": "secret" },
+      "zone_id": "zone2",
+      "subdomains": ["vpn"]
+    }
+  ],
+  "a": true,
+  "aaaa": false
+}"#;
+        let import = ImportFormat::CloudflareDdns.parse(text)?;
+        let a = |zone: &str, name: &str| ImportHost {
+            record_type: Some("A".to_string()),
+            ..host(Some(zone), name)
+        };
+        assert_eq!(
+            import.hosts,
+            vec![a("zone1", ""), a("zone1", "www"), a("zone2", "vpn")]
+        );
+        assert_eq!(import.tokens, vec!["secret"]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_configs_without_hosts() {
+        let text = "protocol=dyndns2 login=me password=secret example.com";
+        assert!(ImportFormat::Ddclient.parse(text).is_err());
+    }
+
+    #[test]
+    fn keeps_distinct_tokens() -> Result<()> {
+        let text = "\
+protocol=cloudflare login=token password=a www.imbleau.com
+protocol=cloudflare login=token password=a imbleau.com
+protocol=cloudflare login=token password=b example.com
+";
+        let import = ImportFormat::Ddclient.parse(text)?;
+        assert_eq!(import.tokens, vec!["a", "b"]);
+        assert_eq!(import.token(), Some("a"));
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_hosts() {
+        let imbleau = zone("z1", "imbleau.com");
+        let sub = zone("z2", "dev.imbleau.com");
+        let records = vec![
+            record("r1", &imbleau, "imbleau.com", "A"),
+            record("r2", &imbleau, "www.imbleau.com", "A"),
+            record("r3", &imbleau, "www.imbleau.com", "AAAA"),
+            record("r4", &sub, "api.dev.imbleau.com", "A"),
+        ];
+        let import = Import {
+            hosts: vec![
+                host(Some("imbleau.com"), "@"),
+                ImportHost {
+                    record_type: Some("AAAA".to_string()),
+                    ..host(Some("z1"), "www")
+                },
+                host(None, "api.dev.imbleau.com"),
+                host(None, "missing.imbleau.com"),
+                host(None, "www.example.com"),
+            ],
+            tokens: vec![],
+        };
+        let (data, unresolved) =
+            import.resolve(&[imbleau.clone(), sub.clone()], &records);
+        let plain = |id: &str| InventoryRecord::Plain(id.to_string());
+        assert_eq!(
+            data.records(),
+            vec![
+                ("z1".to_string(), vec![plain("r1"), plain("r3")]),
+                ("z2".to_string(), vec![plain("r4")]),
+            ]
+        );
+        assert_eq!(unresolved, vec!["missing.imbleau.com", "www.example.com"]);
+    }
+
+    #[test]
+    fn qualifies_names() {
+        assert_eq!(qualify("@", "imbleau.com"), "imbleau.com");
+        assert_eq!(qualify("", "imbleau.com"), "imbleau.com");
+        assert_eq!(qualify("www", "imbleau.com"), "www.imbleau.com");
+        assert_eq!(
+            qualify("www.imbleau.com.", "imbleau.com"),
+            "www.imbleau.com"
+        );
+        assert_eq!(
+            qualify("wwwimbleau.com", "imbleau.com"),
+            "wwwimbleau.com.imbleau.com"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::inventory::models::InventoryData;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 /// An iterator over the zones and corresponding records.
 pub struct InventoryIter {
@@ -29,13 +29,13 @@ impl IntoIterator for InventoryData {
     type IntoIter = InventoryIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        let mut items: HashMap<String, Vec<String>> = HashMap::new();
+        let mut items: IndexMap<String, Vec<String>> = IndexMap::new();
         if let Some(map) = self.0 {
             for (key, value) in map {
                 let entry = items.entry(key.clone()).or_default();
                 if let Some(record_set) = value.0 {
                     for record in record_set {
-                        entry.push(record.0.clone());
+                        entry.push(record.key().to_string());
                     }
                 }
             }
@@ -0,0 +1,689 @@
+use crate::cloudflare::models::{Record, Zone};
+use crate::inventory::editor::{LocatedZone, TomlEditor, YamlEditor};
+use crate::inventory::models::{InventoryFormat, InventoryRecord};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// The severity of a lint issue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// An issue found in an inventory file.
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Display for Issue {
+    /// Format the issue like a compiler diagnostic, e.g.
+    /// `inventory.yml:3: error: invalid hostname`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// An inventory file parsed for linting.
+#[derive(Clone, Debug)]
+pub struct LintFile {
+    pub path: PathBuf,
+    pub zones: Vec<LocatedZone>,
+}
+
+/// A linter for inventory files, which reports issues with line numbers.
+#[derive(Default)]
+pub struct Linter {
+    files: Vec<LintFile>,
+    issues: Vec<Issue>,
+}
+
+impl Linter {
+    /// Create a new linter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse and lint an inventory file, without network access.
+    pub fn add(
+        &mut self,
+        path: impl AsRef<Path>,
+        text: &str,
+        format: InventoryFormat,
+    ) {
+        let path = path.as_ref().to_owned();
+        match locate(text, format) {
+            Ok(zones) => {
+                let zones = self.expand(&path, zones);
+                let file = LintFile { path, zones };
+                self.lint_file(&file);
+                self.files.push(file);
+            }
+            Err(err) => self.issues.push(Issue {
+                path,
+                line: None,
+                severity: Severity::Error,
+                message: format!("{err:#}"),
+            }),
+        }
+    }
+
+    /// Lint records which are listed in more than one file.
+    pub fn lint_fragments(&mut self) {
+        let mut seen: HashMap<(&str, Identity), (&Path, usize)> =
+            HashMap::new();
+        let mut issues = vec![];
+        for file in self.files.iter() {
+            let mut listed = vec![];
+            for zone in file.zones.iter() {
+                for (record, line) in zone.records.iter() {
+                    let key = (zone.zone.as_str(), identity(record));
+                    match seen.get(&key) {
+                        Some((path, first)) if *path != file.path => {
+                            issues.push(Issue {
+                                path: file.path.clone(),
+                                line: Some(*line),
+                                severity: Severity::Warning,
+                                message: format!(
+                                    "duplicate record '{record}', also listed in '{}' on line {first}",
+                                    path.display()
+                                ),
+                            })
+                        }
+                        Some(_) => {}
+                        None => listed.push((key, *line)),
+                    }
+                }
+            }
+            for (key, line) in listed {
+                seen.entry(key).or_insert((&file.path, line));
+            }
+        }
+        self.issues.extend(issues);
+    }
+
+    /// Lint entries against Cloudflare, reporting entries which can not be
+    /// resolved and resources listed under both their id and their name.
+    pub fn lint_online(&mut self, zones: &[Zone], records: &[Record]) {
+        let mut issues = vec![];
+        let mut seen_zones: HashMap<&str, (&str, &Path, usize)> =
+            HashMap::new();
+        let mut seen_records: HashMap<&str, (&str, &Path, usize)> =
+            HashMap::new();
+        for file in self.files.iter() {
+            let issue = |line: usize, severity, message| Issue {
+                path: file.path.clone(),
+                line: Some(line),
+                severity,
+                message,
+            };
+            for zone in file.zones.iter() {
+                let cf_zone = zones
+                    .iter()
+                    .find(|z| z.id == zone.zone || z.name == zone.zone);
+                let Some(cf_zone) = cf_zone else {
+                    issues.push(issue(
+                        zone.line,
+                        Severity::Error,
+                        format!("zone '{}' not found on cloudflare", zone.zone),
+                    ));
+                    continue;
+                };
+                match seen_zones.get(cf_zone.id.as_str()) {
+                    Some((listed, path, line)) if *listed != zone.zone => {
+                        issues.push(issue(
+                            zone.line,
+                            Severity::Warning,
+                            format!(
+                                "zone '{}' is also listed as '{listed}' in '{}' on line {line}",
+                                zone.zone,
+                                path.display()
+                            ),
+                        ))
+                    }
+                    Some(_) => {}
+                    None => {
+                        seen_zones.insert(
+                            &cf_zone.id,
+                            (&zone.zone, &file.path, zone.line),
+                        );
+                    }
+                }
+                for (listed, line) in zone.records.iter() {
+                    let record = listed.key();
+                    if let Ok(Some(pattern)) = listed.pattern() {
+                        let matches = records.iter().any(|r| {
+                            r.zone_id == cf_zone.id && pattern.is_match(&r.name)
+                        });
+                        if !matches {
+                            issues.push(issue(
+                                *line,
+                                Severity::Warning,
+                                format!(
+                                    "pattern '{record}' matches no records in zone '{}' on cloudflare",
+                                    cf_zone.name
+                                ),
+                            ));
+                        }
+                        continue;
+                    }
+                    let cf_record = records.iter().find(|r| {
+                        r.zone_id == cf_zone.id
+                            && (listed.refers_to(r) || listed.relinks_to(r))
+                    });
+                    let Some(cf_record) = cf_record else {
+                        issues.push(issue(
+                            *line,
+                            Severity::Error,
+                            format!(
+                                "record '{record}' not found in zone '{}' on cloudflare",
+                                cf_zone.name
+                            ),
+                        ));
+                        continue;
+                    };
+                    if !matches!(cf_record.record_type.as_str(), "A" | "AAAA") {
+                        issues.push(issue(
+                            *line,
+                            Severity::Warning,
+                            format!(
+                                "record '{record}' has unsupported type '{}'",
+                                cf_record.record_type
+                            ),
+                        ));
+                    }
+                    match seen_records.get(cf_record.id.as_str()) {
+                        Some((listed, path, first)) if *listed != record => {
+                            issues.push(issue(
+                                *line,
+                                Severity::Warning,
+                                format!(
+                                    "record '{record}' is also listed as '{listed}' in '{}' on line {first}",
+                                    path.display()
+                                ),
+                            ))
+                        }
+                        Some(_) => {}
+                        None => {
+                            seen_records
+                                .insert(&cf_record.id, (record, &file.path, *line));
+                        }
+                    }
+                }
+            }
+        }
+        self.issues.extend(issues);
+    }
+
+    /// Return the issues found, ordered by path and line.
+    pub fn issues(&self) -> Vec<Issue> {
+        let mut issues = self.issues.clone();
+        issues.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        issues
+    }
+
+    /// Expand the templates in zones and records, as they are when the
+    /// inventory is read. Entries which fail to expand are reported, then
+    /// skipped.
+    fn expand(
+        &mut self,
+        path: &Path,
+        zones: Vec<LocatedZone>,
+    ) -> Vec<LocatedZone> {
+        let mut issue = |line: usize, err: anyhow::Error| {
+            self.issues.push(Issue {
+                path: path.to_owned(),
+                line: Some(line),
+                severity: Severity::Error,
+                message: format!("{err:#}"),
+            })
+        };
+        let mut expanded = vec![];
+        for mut zone in zones {
+            match crate::util::template::expand(&zone.zone) {
+                Ok(name) => zone.zone = name,
+                Err(err) => {
+                    issue(zone.line, err);
+                    continue;
+                }
+            }
+            let mut records = vec![];
+            for (record, line) in zone.records {
+                match record.expand() {
+                    Ok(record) => records.push((record, line)),
+                    Err(err) => issue(line, err),
+                }
+            }
+            zone.records = records;
+            expanded.push(zone);
+        }
+        expanded
+    }
+
+    /// Lint a single file.
+    fn lint_file(&mut self, file: &LintFile) {
+        let issue = |line: usize, severity, message| Issue {
+            path: file.path.clone(),
+            line: Some(line),
+            severity,
+            message,
+        };
+        let mut zones_seen: HashMap<&str, usize> = HashMap::new();
+        for zone in file.zones.iter() {
+            if let Some(first) = zones_seen.get(zone.zone.as_str()) {
+                self.issues.push(issue(
+                    zone.line,
+                    Severity::Warning,
+                    format!(
+                        "duplicate zone '{}', first listed on line {first}",
+                        zone.zone
+                    ),
+                ));
+            } else {
+                zones_seen.insert(&zone.zone, zone.line);
+            }
+            if let Some(err) = hostname_error(&zone.zone) {
+                self.issues.push(issue(
+                    zone.line,
+                    Severity::Error,
+                    format!("invalid zone '{}': {err}", zone.zone),
+                ));
+            }
+            if zone.records.is_empty() {
+                self.issues.push(issue(
+                    zone.line,
+                    Severity::Warning,
+                    format!("zone '{}' has no records", zone.zone),
+                ));
+            }
+
+            let mut records_seen: HashMap<Identity, usize> = HashMap::new();
+            for (listed, line) in zone.records.iter() {
+                let record = listed.key();
+                if let Some(first) = records_seen.get(&identity(listed)) {
+                    self.issues.push(issue(
+                        *line,
+                        Severity::Warning,
+                        format!(
+                            "duplicate record '{record}', first listed on line {first}"
+                        ),
+                    ));
+                    continue;
+                }
+                records_seen.insert(identity(listed), *line);
+                if listed.is_pattern() {
+                    if let Err(err) = listed.pattern() {
+                        // Regex errors span lines, keep the reason only
+                        let reason = err.root_cause().to_string();
+                        let reason = reason.lines().last().unwrap_or_default();
+                        self.issues.push(issue(
+                            *line,
+                            Severity::Error,
+                            format!(
+                                "invalid pattern '{record}': {}",
+                                reason.trim_start_matches("error: ")
+                            ),
+                        ));
+                    }
+                } else if let Some(err) = hostname_error(record) {
+                    self.issues.push(issue(
+                        *line,
+                        Severity::Error,
+                        format!("invalid record '{record}': {err}"),
+                    ));
+                } else if !is_cloudflare_id(&zone.zone)
+                    && !is_cloudflare_id(record)
+                    && !is_in_zone(record, &zone.zone)
+                {
+                    self.issues.push(issue(
+                        *line,
+                        Severity::Error,
+                        format!(
+                            "record '{record}' is outside of zone '{}'",
+                            zone.zone
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Parse an inventory file into zones and records with their line numbers.
+fn locate(text: &str, format: InventoryFormat) -> Result<Vec<LocatedZone>> {
+    if format == InventoryFormat::Yaml {
+        if let Ok(editor) = YamlEditor::parse(text) {
+            return Ok(editor.located());
+        }
+    }
+    let entries = match format {
+        InventoryFormat::Toml => TomlEditor::parse(text)?.entries(),
+        _ => {
+            let map: IndexMap<String, Option<Vec<InventoryRecord>>> =
+                match format {
+                    InventoryFormat::Json => serde_json::from_str(text)
+                        .context("deserializing inventory as JSON")?,
+                    _ => serde_yaml::from_str::<Option<_>>(text)
+                        .context("deserializing inventory as YAML")?
+                        .unwrap_or_default(),
+                };
+            map.into_iter()
+                .map(|(zone, records)| (zone, records.unwrap_or_default()))
+                .collect()
+        }
+    };
+    Ok(scan_lines(text, entries))
+}
+
+/// Find the line numbers of entries by scanning the text in order.
+fn scan_lines(
+    text: &str,
+    entries: Vec<(String, Vec<InventoryRecord>)>,
+) -> Vec<LocatedZone> {
+    let lines = text.lines().collect::<Vec<&str>>();
+    let find = |from: usize, matches: &dyn Fn(&str) -> bool| {
+        (from..lines.len()).find(|idx| matches(lines[*idx]))
+    };
+    let quoted = |value: &str, line: &str| {
+        line.contains(&format!("\"{value}\""))
+            || line.contains(&format!("'{value}'"))
+    };
+    let mut cursor = 0;
+    let mut zones = vec![];
+    for (zone, records) in entries {
+        let zone_idx = find(cursor, &|line| {
+            let line = line.trim_start();
+            quoted(&zone, line) || line.starts_with(zone.as_str())
+        })
+        .unwrap_or(cursor);
+        let mut record_cursor = zone_idx;
+        let mut located = vec![];
+        for record in records {
+            let key = record.key();
+            let record_idx = find(record_cursor, &|line| {
+                quoted(key, line)
+                    || line.trim_start().trim_start_matches("- ") == key
+            })
+            .unwrap_or(record_cursor);
+            located.push((record, record_idx + 1));
+            record_cursor = record_idx;
+        }
+        zones.push(LocatedZone {
+            zone,
+            line: zone_idx + 1,
+            records: located,
+        });
+        cursor = zone_idx + 1;
+    }
+    zones
+}
+
+/// The name, type and id an entry is listed under.
+type Identity<'a> = (&'a str, Option<&'a str>, Option<&'a str>);
+
+/// Return the identity of an entry, so records sharing a name but listed
+/// with a different type or id, e.g. an A and an AAAA record, are told apart.
+fn identity(record: &InventoryRecord) -> Identity<'_> {
+    (record.key(), record.record_type(), record.id())
+}
+
+/// Returns whether a value looks like a Cloudflare identifier.
+pub fn is_cloudflare_id(value: &str) -> bool {
+    value.len() == 32 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Returns whether a record name belongs to a zone name.
+fn is_in_zone(record: &str, zone: &str) -> bool {
+    let (record, zone) = (record.to_lowercase(), zone.to_lowercase());
+    record == zone || record.ends_with(&format!(".{zone}"))
+}
+
+/// Return why a name is not a valid hostname, if it isn't. Cloudflare
+/// identifiers are accepted.
+fn hostname_error(name: &str) -> Option<String> {
+    if is_cloudflare_id(name) {
+        return None;
+    }
+    if name.is_empty() {
+        return Some("empty name".to_string());
+    }
+    if name.ends_with('.') {
+        return Some("trailing dot".to_string());
+    }
+    if !name.is_ascii() {
+        return Some("non-ASCII characters, use punycode".to_string());
+    }
+    if name.len() > 253 {
+        return Some("longer than 253 characters".to_string());
+    }
+    for (idx, label) in name.split('.').enumerate() {
+        if idx == 0 && label == "*" {
+            continue;
+        }
+        if label.is_empty() {
+            return Some("empty label".to_string());
+        }
+        if label.len() > 63 {
+            return Some(format!(
+                "label '{label}' is longer than 63 characters"
+            ));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Some(format!("label '{label}' starts or ends with '-'"));
+        }
+        if let Some(c) = label
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+        {
+            return Some(format!("invalid character '{c}'"));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(
+        text: &str,
+        format: InventoryFormat,
+    ) -> Vec<(Option<usize>, Severity, String)> {
+        let mut linter = Linter::new();
+        linter.add("inventory.yml", text, format);
+        linter
+            .issues()
+            .into_iter()
+            .map(|issue| (issue.line, issue.severity, issue.message))
+            .collect()
+    }
+
+    #[test]
+    fn clean_inventory_has_no_issues() {
+        let text = "imbleau.com:\n  - imbleau.com\n  - www.imbleau.com\n  - 372e67954025e0ba6aaa6d586b9e0b59\n";
+        assert!(lint(text, InventoryFormat::Yaml).is_empty());
+    }
+
+    #[test]
+    fn reports_issues_with_line_numbers() {
+        let text = "\
+imbleau.com:
+  - www.imbleau.com
+  - www.imbleau.com
+  - www.example.com
+  - bad_.imbleau.com.
+example.com:
+";
+        let issues = lint(text, InventoryFormat::Yaml);
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    Some(3),
+                    Severity::Warning,
+                    "duplicate record 'www.imbleau.com', first listed on line 2"
+                        .to_string()
+                ),
+                (
+                    Some(4),
+                    Severity::Error,
+                    "record 'www.example.com' is outside of zone 'imbleau.com'"
+                        .to_string()
+                ),
+                (
+                    Some(5),
+                    Severity::Error,
+                    "invalid record 'bad_.imbleau.com.': trailing dot"
+                        .to_string()
+                ),
+                (
+                    Some(6),
+                    Severity::Warning,
+                    "zone 'example.com' has no records".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn tells_records_sharing_a_name_apart() {
+        let text = "\
+imbleau.com:
+  - {name: www.imbleau.com, id: '1', type: A}
+  - {name: www.imbleau.com, id: '2', type: AAAA}
+  - {name: www.imbleau.com, id: '2', type: AAAA, groups: [web]}
+  - www.imbleau.com
+  - www.imbleau.com
+";
+        let issues = lint(text, InventoryFormat::Yaml);
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    Some(4),
+                    Severity::Warning,
+                    "duplicate record 'www.imbleau.com', first listed on line 3"
+                        .to_string()
+                ),
+                (
+                    Some(6),
+                    Severity::Warning,
+                    "duplicate record 'www.imbleau.com', first listed on line 5"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_patterns() {
+        let text = "\
+imbleau.com:
+  - {glob: '*.svc.imbleau.com'}
+  - {regex: 'web-(\\d+'}
+";
+        let issues = lint(text, InventoryFormat::Yaml);
+        assert_eq!(issues.len(), 1);
+        let (line, severity, message) = &issues[0];
+        assert_eq!((*line, *severity), (Some(3), Severity::Error));
+        assert!(message.starts_with("invalid pattern 'web-(\\d+'"));
+    }
+
+    #[test]
+    fn locates_lines_in_toml_and_json() {
+        let toml = "\
+# zones
+\"imbleau.com\" = [
+  \"www.imbleau.com\",
+  \"www.example.com\",
+]
+";
+        assert_eq!(
+            lint(toml, InventoryFormat::Toml)
+                .into_iter()
+                .map(|(line, ..)| line)
+                .collect::<Vec<_>>(),
+            vec![Some(4)]
+        );
+        let json = "{\n  \"imbleau.com\": [\n    \"www.example.com\"\n  ]\n}\n";
+        assert_eq!(
+            lint(json, InventoryFormat::Json)
+                .into_iter()
+                .map(|(line, ..)| line)
+                .collect::<Vec<_>>(),
+            vec![Some(3)]
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors_without_line() {
+        let issues = lint("imbleau.com: [", InventoryFormat::Yaml);
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].0, issues[0].1), (None, Severity::Error));
+    }
+
+    #[test]
+    fn reports_records_listed_in_several_fragments() {
+        let mut linter = Linter::new();
+        let text = "imbleau.com:\n  - www.imbleau.com\n";
+        linter.add("a.yml", text, InventoryFormat::Yaml);
+        linter.add("b.yml", text, InventoryFormat::Yaml);
+        linter.lint_fragments();
+        let issues = linter.issues();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].to_string(),
+            "b.yml:2: warning: duplicate record 'www.imbleau.com', also listed in 'a.yml' on line 2"
+        );
+    }
+
+    #[test]
+    fn validates_hostnames() {
+        assert_eq!(hostname_error("*.imbleau.com"), None);
+        assert_eq!(hostname_error("_dmarc.imbleau.com"), None);
+        assert_eq!(
+            hostname_error("www..imbleau.com").as_deref(),
+            Some("empty label")
+        );
+        assert_eq!(
+            hostname_error("-www.imbleau.com").as_deref(),
+            Some("label '-www' starts or ends with '-'")
+        );
+        assert_eq!(
+            hostname_error("wéb.imbleau.com").as_deref(),
+            Some("non-ASCII characters, use punycode")
+        );
+        assert_eq!(
+            hostname_error(&format!("{}.com", "a".repeat(64))).as_deref(),
+            Some(
+                format!(
+                    "label '{}' is longer than 63 characters",
+                    "a".repeat(64)
+                )
+                .as_str()
+            )
+        );
+        assert!(is_in_zone("WWW.Imbleau.com", "imbleau.com"));
+        assert!(!is_in_zone("wwwimbleau.com", "imbleau.com"));
+    }
+}
@@ -12,8 +12,14 @@
 //! ```
 
 pub mod builder;
+pub mod editor;
+pub mod export;
+pub mod import;
 pub mod iter;
+pub mod lint;
 pub mod models;
+pub mod plan;
+pub mod report;
 
 /// Return the default inventory path, depending on the host OS.
 ///
@@ -1,18 +1,29 @@
+use crate::cloudflare::models::Record;
 use crate::config::models::ConfigOpts;
 use crate::inventory::builder::InventoryBuilder;
+use crate::inventory::default_inventory_path;
+use crate::inventory::editor::{InventoryEditor, TomlEditor};
 use crate::util::postprocessors::{
     InventoryAliasCommentPostProcessor, PostProcessor, TimestampPostProcessor,
 };
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use indexmap::{IndexMap, IndexSet};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use tracing::debug;
+use tracing::{debug, warn};
 
 #[derive(Clone, Debug)]
 pub struct Inventory {
     pub path: PathBuf,
     pub data: InventoryData,
+    /// The original file contents, edited in place on save.
+    pub source: Option<String>,
+    pub format: InventoryFormat,
+    /// Whether the data was expanded from templates in the file. Entries
+    /// using templates are then saved as written.
+    pub expanded: bool,
 }
 
 impl Inventory {
@@ -21,8 +32,12 @@ impl Inventory {
         InventoryBuilder::new()
     }
 
-    /// Read inventory from a target path.
-    pub async fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+    /// Read inventory from a target path. The format is detected from the
+    /// file extension, unless given.
+    pub async fn from_file(
+        path: impl AsRef<Path>,
+        format: Option<InventoryFormat>,
+    ) -> Result<Self> {
         let path = path.as_ref();
         debug!("reading inventory path: '{}'", path.display());
         if !path.exists() {
@@ -39,55 +54,803 @@ impl Inventory {
         let contents = tokio::fs::read_to_string(&path)
             .await
             .context("reading inventory file")?;
-        Inventory::builder()
+        let mut inventory = Inventory::builder()
             .path(path)
+            .format(format)
             .with_bytes(contents.as_bytes())?
-            .build()
+            .build()?;
+        let expanded = inventory.data.expand()?;
+        if expanded != inventory.data {
+            debug!("expanded templates in inventory");
+            inventory.data = expanded;
+            inventory.expanded = true;
+        }
+        Ok(inventory)
+    }
+
+    /// Return the inventory as a processed string. If the inventory was read
+    /// from a file, the file is edited in place to keep comments and order.
+    pub async fn to_string(
+        &self,
+        opts: &ConfigOpts, // TODO: This shouldn't be necessary...
+        friendly_names: bool, // Postprocess friendly aliases to the inventory
+        timestamp: bool,   // Postprocess a timestamp to the header
+    ) -> Result<String> {
+        let format = self.format;
+        let Some(source) = self.source.as_ref() else {
+            return self
+                .data
+                .to_string(opts, format, friendly_names, timestamp)
+                .await;
+        };
+        let data = match self.expanded {
+            true => self.unexpanded(source)?,
+            false => self.data.clone(),
+        };
+        if !format.supports_comments() {
+            // Without comments to keep, only the order is preserved
+            let contents = format.encode(&data)?;
+            return post_process(
+                contents,
+                opts,
+                format,
+                friendly_names,
+                timestamp,
+            )
+            .await;
+        }
+        match InventoryEditor::parse(source, format) {
+            Ok(mut editor) => {
+                editor.apply(&data);
+                post_process(
+                    editor.to_string(),
+                    opts,
+                    format,
+                    friendly_names,
+                    timestamp,
+                )
+                .await
+            }
+            Err(err) => {
+                warn!("{err:#}, inventory file will be rewritten");
+                data.to_string(opts, format, friendly_names, timestamp)
+                    .await
+            }
+        }
     }
 
     /// Save the inventory file at the given path, overwriting if necessary.
+    /// Returns whether the file was written, which it is not in a dry run.
     pub async fn save(
         &self,
         opts: &ConfigOpts, // TODO: This shouldn't be necessary...
         friendly_names: bool, // Postprocess friendly aliases to the inventory
         timestamp: bool,   // Postprocess a timestamp to the header
-    ) -> Result<()> {
-        let yaml = self.data.to_string(opts, friendly_names, timestamp).await?;
-        crate::util::fs::save(&self.path, yaml).await
+    ) -> Result<bool> {
+        let contents = self.to_string(opts, friendly_names, timestamp).await?;
+        let backups = opts.inventory.backups.unwrap_or_default();
+        let dry_run = opts.inventory.dry_run.unwrap_or_default();
+        crate::util::fs::save(&self.path, contents, backups, dry_run).await
+    }
+
+    /// Return the data as written in the file, with the changes made to the
+    /// expanded data. Entries using templates can not be changed, so they are
+    /// kept as written, while other entries are added or removed.
+    fn unexpanded(&self, source: &str) -> Result<InventoryData> {
+        let expand = crate::util::template::expand;
+        let written = self.format.decode(source.as_bytes())?;
+        let mut data = InventoryData(None);
+        let mut kept = InventoryData(None);
+        let mut zones: HashMap<String, String> = HashMap::new();
+        for (written_zone, records) in written.records() {
+            let zone = expand(&written_zone)?;
+            zones.entry(zone.clone()).or_insert(written_zone.clone());
+            if written_zone != zone {
+                data.0
+                    .get_or_insert(IndexMap::new())
+                    .entry(written_zone.clone())
+                    .or_insert_with(|| InventoryZone(None));
+            }
+            for record in records {
+                let expanded = record.expand()?;
+                let present = self.data.contains_record(&zone, &expanded);
+                if expanded != record {
+                    if !present {
+                        warn!(
+                            zone = written_zone,
+                            record = record.key(),
+                            "entry uses templates and is kept, edit it by hand"
+                        );
+                    }
+                } else if !present {
+                    continue;
+                }
+                data.insert_record(&written_zone, record);
+                kept.insert_record(&zone, expanded);
+            }
+        }
+        // New entries are listed under their zone as written
+        for (zone, records) in self.data.records() {
+            let written_zone = zones.get(&zone).unwrap_or(&zone);
+            data.0
+                .get_or_insert(IndexMap::new())
+                .entry(written_zone.clone())
+                .or_insert_with(|| InventoryZone(None));
+            for record in records {
+                if !kept.contains_record(&zone, &record) {
+                    data.insert_record(written_zone, record);
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// An inventory merged from the inventory file and its fragments, such as the
+/// files in an `inventory.d` directory beside it. Changes are written back to
+/// the file each entry came from.
+#[derive(Clone, Debug)]
+pub struct InventorySet {
+    /// The inventory file, which receives entries for new zones.
+    pub path: PathBuf,
+    /// The inventory file, if it exists, followed by its fragments.
+    pub fragments: Vec<Inventory>,
+    /// The indices of fragments with unsaved changes.
+    modified: HashSet<usize>,
+}
+
+impl InventorySet {
+    /// Read the inventory file and the fragments it includes.
+    pub async fn from_opts(opts: &ConfigOpts) -> Result<Self> {
+        let path = opts
+            .inventory
+            .path
+            .clone()
+            .unwrap_or_else(default_inventory_path);
+        let mut fragments = vec![];
+        for (fragment, format) in Self::files(opts)? {
+            // Keep the cause, such as a variable which is not set, visible
+            let inventory = Inventory::from_file(&fragment, format)
+                .await
+                .map_err(|err| {
+                    anyhow!(
+                        "reading inventory '{}': {err:#}",
+                        fragment.display()
+                    )
+                })?;
+            fragments.push(inventory);
+        }
+        let set = Self {
+            path,
+            fragments,
+            modified: HashSet::new(),
+        };
+        for (zone, record, paths) in set.duplicates() {
+            warn!(
+                zone,
+                record = record.to_string(),
+                "duplicate inventory record in {}",
+                paths
+                    .iter()
+                    .map(|p| format!("'{}'", p.display()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+        }
+        Ok(set)
+    }
+
+    /// Return the inventory file, if it exists, followed by the fragments it
+    /// includes, with any explicit format for each.
+    pub fn files(
+        opts: &ConfigOpts,
+    ) -> Result<Vec<(PathBuf, Option<InventoryFormat>)>> {
+        let path = opts
+            .inventory
+            .path
+            .clone()
+            .unwrap_or_else(default_inventory_path);
+        let mut paths = vec![];
+        if path.exists() {
+            let path = path.canonicalize().with_context(|| {
+                format!(
+                    "getting canonical path to inventory file '{}'",
+                    path.display()
+                )
+            })?;
+            paths.push(path);
+        }
+        let mut includes = opts.inventory.include.clone().unwrap_or_default();
+        let fragment_dir = path.with_file_name("inventory.d");
+        if fragment_dir.is_dir() {
+            includes.insert(0, fragment_dir.display().to_string());
+        }
+        for include in includes {
+            for fragment in fragment_paths(&include)? {
+                if !paths.iter().any(|p| same_file(p, &fragment)) {
+                    paths.push(fragment);
+                }
+            }
+        }
+        if paths.is_empty() {
+            bail!("inventory file not found, need help? see https://github.com/simbleau/cddns#readme");
+        }
+        // An explicit format only applies to the inventory file
+        Ok(paths
+            .into_iter()
+            .map(|fragment| {
+                let format = match same_file(&fragment, &path) {
+                    true => opts.inventory.format,
+                    false => None,
+                };
+                (fragment, format)
+            })
+            .collect())
+    }
+
+    /// Return the merged inventory data of all fragments.
+    pub fn data(&self) -> InventoryData {
+        let mut data = InventoryData(None);
+        for fragment in self.fragments.iter() {
+            for (zone, records) in fragment.data.records() {
+                data.0
+                    .get_or_insert(IndexMap::new())
+                    .entry(zone.clone())
+                    .or_insert_with(|| InventoryZone(None));
+                for record in records {
+                    data.insert_record(&zone, record);
+                }
+            }
+        }
+        data
+    }
+
+    /// Return the records which are listed more than once, across all
+    /// fragments, with the paths they are listed in.
+    pub fn duplicates(&self) -> Vec<(String, InventoryRecord, Vec<PathBuf>)> {
+        let mut seen: IndexMap<(String, InventoryRecord), Vec<PathBuf>> =
+            IndexMap::new();
+        for fragment in self.fragments.iter() {
+            for (zone, records) in fragment.data.records() {
+                for record in records {
+                    seen.entry((zone.clone(), record))
+                        .or_default()
+                        .push(fragment.path.clone());
+                }
+            }
+        }
+        seen.into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|((zone, record), paths)| (zone, record, paths))
+            .collect()
+    }
+
+    /// Insert a record model into the fragment which lists its zone, or the
+    /// inventory file if no fragment does.
+    pub fn insert_record(
+        &mut self,
+        zone_id: impl Into<String>,
+        record: InventoryRecord,
+    ) {
+        let zone_id = zone_id.into();
+        let owner = self.fragments.iter().position(|fragment| {
+            fragment
+                .data
+                .0
+                .as_ref()
+                .is_some_and(|map| map.contains_key(&zone_id))
+        });
+        let idx = match owner {
+            Some(idx) => idx,
+            None => self.main(),
+        };
+        self.fragments[idx].data.insert_record(zone_id, record);
+        self.modified.insert(idx);
+    }
+
+    /// Remove a record model, compared as a whole, from every fragment which
+    /// lists it. Returns whether the record was present.
+    pub fn remove_record(
+        &mut self,
+        zone_id: impl Into<String>,
+        record: &InventoryRecord,
+    ) -> bool {
+        let zone_id = zone_id.into();
+        let mut removed = false;
+        for (idx, fragment) in self.fragments.iter_mut().enumerate() {
+            if fragment.data.remove_record(&zone_id, record) {
+                self.modified.insert(idx);
+                removed = true;
+            }
+        }
+        removed
+    }
+
+    /// Replace a record model, compared as a whole, in every fragment which
+    /// lists it. Returns whether the record was present.
+    pub fn replace(
+        &mut self,
+        zone_id: impl Into<String>,
+        previous: &InventoryRecord,
+        record: InventoryRecord,
+    ) -> bool {
+        let zone_id = zone_id.into();
+        let mut replaced = false;
+        for (idx, fragment) in self.fragments.iter_mut().enumerate() {
+            if fragment.data.replace(&zone_id, previous, record.clone()) {
+                self.modified.insert(idx);
+                replaced = true;
+            }
+        }
+        replaced
+    }
+
+    /// Replace the merged inventory data, writing removals and additions back
+    /// to the fragments they belong to. Records are compared as a whole, so
+    /// patterns and details are kept as they are.
+    pub fn set_data(&mut self, data: &InventoryData) {
+        // Add first, so records join the fragment owning their zone
+        let previous = self.data();
+        for (zone, records) in data.records() {
+            for record in records {
+                if !previous.contains_record(&zone, &record) {
+                    self.insert_record(&zone, record);
+                }
+            }
+        }
+        for (zone, records) in previous.records() {
+            for record in records {
+                if !data.contains_record(&zone, &record) {
+                    self.remove_record(&zone, &record);
+                }
+            }
+        }
+    }
+
+    /// Save the fragments with unsaved changes. Returns whether they were
+    /// written, which they are not in a dry run.
+    pub async fn save(
+        &mut self,
+        opts: &ConfigOpts, // TODO: This shouldn't be necessary...
+        friendly_names: bool, // Postprocess friendly aliases to the inventory
+        timestamp: bool,   // Postprocess a timestamp to the header
+    ) -> Result<bool> {
+        let mut modified = self.modified.drain().collect::<Vec<usize>>();
+        modified.sort();
+        let mut written = true;
+        for idx in modified {
+            let fragment = &self.fragments[idx];
+            debug!("saving inventory '{}'", fragment.path.display());
+            written &= fragment.save(opts, friendly_names, timestamp).await?;
+        }
+        Ok(written)
+    }
+
+    /// Return the index of the inventory file, creating it if necessary.
+    fn main(&mut self) -> usize {
+        if let Some(idx) = self
+            .fragments
+            .iter()
+            .position(|f| same_file(&f.path, &self.path))
+        {
+            return idx;
+        }
+        self.fragments.insert(
+            0,
+            Inventory {
+                path: self.path.clone(),
+                data: InventoryData(None),
+                source: None,
+                format: InventoryFormat::resolve(None, Some(&self.path)),
+                expanded: false,
+            },
+        );
+        self.modified = self.modified.drain().map(|idx| idx + 1).collect();
+        0
+    }
+}
+
+/// Return the inventory files for an include, which may be a file, a
+/// directory of inventory files, or a glob.
+fn fragment_paths(include: &str) -> Result<Vec<PathBuf>> {
+    if crate::util::glob::is_glob(include) {
+        return crate::util::glob::paths(include);
+    }
+    let path = PathBuf::from(include);
+    if !path.is_dir() {
+        return Ok(vec![path]);
+    }
+    let mut paths = std::fs::read_dir(&path)
+        .with_context(|| format!("reading directory '{}'", path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && InventoryFormat::from_path(path).is_some()
+                && !path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Returns whether two paths point to the same file.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
     }
 }
 
-/// The model for DNS record inventory.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InventoryData(pub Option<HashMap<String, InventoryZone>>);
+/// Apply the requested post-processors to inventory contents. Formats without
+/// comments are left as is.
+async fn post_process(
+    mut contents: String,
+    opts: &ConfigOpts,
+    format: InventoryFormat,
+    friendly_names: bool,
+    timestamp: bool,
+) -> Result<String> {
+    if !format.supports_comments() {
+        if friendly_names || timestamp {
+            debug!("skipping post-processing, {format} has no comments");
+        }
+        return Ok(contents);
+    }
+    if friendly_names {
+        // Best-effort attempt to post-process comments on inventory.
+        InventoryAliasCommentPostProcessor::try_init(opts, format)
+            .await?
+            .post_process(&mut contents)?;
+    }
+    if timestamp {
+        TimestampPostProcessor.post_process(&mut contents)?;
+    }
+    Ok(contents)
+}
+
+/// The file format of an inventory.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum InventoryFormat {
+    #[default]
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl InventoryFormat {
+    /// Return the format matching the extension of a path, if any.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    /// Resolve the format of a file, preferring an explicit format, then the
+    /// file extension, then YAML.
+    pub fn resolve(format: Option<Self>, path: Option<&Path>) -> Self {
+        format
+            .or_else(|| path.and_then(Self::from_path))
+            .unwrap_or_default()
+    }
+
+    /// The conventional file extension for the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Json => "json",
+            Self::Toml => "toml",
+        }
+    }
+
+    /// Returns whether the format supports comments.
+    pub fn supports_comments(&self) -> bool {
+        !matches!(self, Self::Json)
+    }
+
+    /// Deserialize inventory data in this format.
+    pub fn decode(&self, bytes: &[u8]) -> Result<InventoryData> {
+        match self {
+            Self::Yaml => serde_yaml::from_slice(bytes)
+                .context("deserializing inventory as YAML"),
+            Self::Json => serde_json::from_slice(bytes)
+                .context("deserializing inventory as JSON"),
+            Self::Toml => {
+                let text = std::str::from_utf8(bytes)
+                    .context("decoding inventory as UTF-8")?;
+                toml::from_str(text).context("deserializing inventory as TOML")
+            }
+        }
+    }
+
+    /// Serialize inventory data in this format, keeping its order.
+    pub fn encode(&self, data: &InventoryData) -> Result<String> {
+        match self {
+            Self::Yaml => crate::util::encoding::as_yaml(data),
+            Self::Json => {
+                crate::util::encoding::as_json(data).map(|json| json + "\n")
+            }
+            Self::Toml => {
+                // TOML has no null, so empty zones are written as `[]`
+                let mut editor = TomlEditor::parse("")?;
+                editor.apply(data);
+                Ok(editor.to_string())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for InventoryFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml => write!(f, "YAML"),
+            Self::Json => write!(f, "JSON"),
+            Self::Toml => write!(f, "TOML"),
+        }
+    }
+}
+
+impl std::str::FromStr for InventoryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        <Self as clap::ValueEnum>::from_str(s, true)
+    }
+}
+
+/// The model for DNS record inventory. Zones and records keep the order in
+/// which they were read or inserted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryData(pub Option<IndexMap<String, InventoryZone>>);
 
 /// The model for a zone with records.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InventoryZone(pub Option<HashSet<InventoryRecord>>);
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryZone(pub Option<IndexSet<InventoryRecord>>);
+
+/// The model for a DNS record, listed by id or name, or detailed with the id
+/// and type it last resolved to and the groups it is tagged with.
+#[derive(
+    Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(untagged)]
+pub enum InventoryRecord {
+    /// A record id or name, e.g. `www.imbleau.com`
+    Plain(String),
+    /// A record id or name with optional details, e.g.
+    /// `{name: www.imbleau.com, id: 5dba009a..., type: A, groups: [web]}`
+    ///
+    /// Records with an id are linked, and re-linked by name and type when
+    /// the id no longer exists.
+    Detailed {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(
+            default,
+            rename = "type",
+            skip_serializing_if = "Option::is_none"
+        )]
+        record_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        groups: Vec<String>,
+    },
+    /// A glob matching record names, where `*` matches within a label and
+    /// `**` across labels, e.g. `{glob: '*.svc.imbleau.com'}`
+    Glob {
+        glob: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        groups: Vec<String>,
+    },
+    /// A regex matching whole record names, e.g.
+    /// `{regex: 'web-\d+\.imbleau\.com'}`
+    Regex {
+        regex: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        groups: Vec<String>,
+    },
+}
+
+impl InventoryRecord {
+    /// Link the record to a Cloudflare record by its name, id and type,
+    /// keeping its groups.
+    pub fn link(&self, record: &Record) -> Self {
+        Self::Detailed {
+            name: record.name.clone(),
+            id: Some(record.id.clone()),
+            record_type: Some(record.record_type.clone()),
+            groups: self.groups().to_vec(),
+        }
+    }
+
+    /// Return the id or name the record is listed as. Detailed records are
+    /// listed by name, and patterns by their pattern.
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Plain(key) => key,
+            Self::Detailed { name, .. } => name,
+            Self::Glob { glob, .. } => glob,
+            Self::Regex { regex, .. } => regex,
+        }
+    }
+
+    /// Return the last-known id of a linked record.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Self::Detailed { id, .. } => id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Return the last-known type of a detailed record.
+    pub fn record_type(&self) -> Option<&str> {
+        match self {
+            Self::Detailed { record_type, .. } => record_type.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Return the groups the record is tagged with.
+    pub fn groups(&self) -> &[String] {
+        match self {
+            Self::Plain(_) => &[],
+            Self::Detailed { groups, .. }
+            | Self::Glob { groups, .. }
+            | Self::Regex { groups, .. } => groups,
+        }
+    }
+
+    /// Returns whether the record is a glob or regex pattern.
+    pub fn is_pattern(&self) -> bool {
+        matches!(self, Self::Glob { .. } | Self::Regex { .. })
+    }
+
+    /// Compile a pattern to a regex matching whole record names.
+    pub fn pattern(&self) -> Result<Option<Regex>> {
+        match self {
+            Self::Glob { glob, .. } => {
+                crate::util::glob::to_hostname_regex(glob).map(Some)
+            }
+            Self::Regex { regex, .. } => Regex::new(&format!("^(?:{regex})$"))
+                .map(Some)
+                .with_context(|| format!("invalid regex '{regex}'")),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns whether the record is listed as, or linked to, an id or name.
+    /// Patterns are never listed as a name, even one with wildcards.
+    pub fn is(&self, id_or_name: &str) -> bool {
+        !self.is_pattern()
+            && (self.key() == id_or_name || self.id() == Some(id_or_name))
+    }
+
+    /// Returns whether the record refers to a Cloudflare record. Linked
+    /// records refer to their last-known id, and patterns never refer to a
+    /// single record.
+    pub fn refers_to(&self, record: &Record) -> bool {
+        match self {
+            Self::Plain(key) => record.id == *key || record.name == *key,
+            Self::Detailed { id: Some(id), .. } => record.id == *id,
+            Self::Detailed { name, .. } => {
+                (record.id == *name || record.name == *name)
+                    && self.has_type(record)
+            }
+            Self::Glob { .. } | Self::Regex { .. } => false,
+        }
+    }
+
+    /// Returns whether a Cloudflare record can replace the last-known id of
+    /// a linked record, having the same name and type.
+    pub fn relinks_to(&self, record: &Record) -> bool {
+        match self {
+            Self::Detailed {
+                name, id: Some(_), ..
+            } => record.name == *name && self.has_type(record),
+            _ => false,
+        }
+    }
+
+    /// Expand the environment variables and `{hostname}` placeholders in
+    /// every field of the record.
+    pub fn expand(&self) -> Result<Self> {
+        use crate::util::template::expand;
+        let expand_all = |groups: &[String]| {
+            groups.iter().map(|g| expand(g)).collect::<Result<Vec<_>>>()
+        };
+        Ok(match self {
+            Self::Plain(key) => Self::Plain(expand(key)?),
+            Self::Detailed {
+                name,
+                id,
+                record_type,
+                groups,
+            } => Self::Detailed {
+                name: expand(name)?,
+                id: id.as_deref().map(expand).transpose()?,
+                record_type: record_type.as_deref().map(expand).transpose()?,
+                groups: expand_all(groups)?,
+            },
+            Self::Glob { glob, groups } => Self::Glob {
+                glob: expand(glob)?,
+                groups: expand_all(groups)?,
+            },
+            Self::Regex { regex, groups } => Self::Regex {
+                regex: expand(regex)?,
+                groups: expand_all(groups)?,
+            },
+        })
+    }
+
+    /// Returns whether a Cloudflare record has the listed type, if any.
+    fn has_type(&self, record: &Record) -> bool {
+        match self {
+            Self::Detailed {
+                record_type: Some(record_type),
+                ..
+            } => record.record_type == *record_type,
+            _ => true,
+        }
+    }
+}
 
-/// The model for a DNS record.
-#[derive(Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
-pub struct InventoryRecord(pub String);
+impl std::fmt::Display for InventoryRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
 
 impl InventoryData {
-    /// Return the inventory as a processed string.
+    /// Return the inventory as a processed string, sorted by zone, then
+    /// record.
     pub async fn to_string(
         &self,
         opts: &ConfigOpts, // TODO: This shouldn't be necessary...
+        format: InventoryFormat,
         friendly_names: bool, // Postprocess friendly aliases to the inventory
-        timestamp: bool,   // Postprocess a timestamp to the header
+        timestamp: bool,      // Postprocess a timestamp to the header
     ) -> Result<String> {
-        let mut data = crate::util::encoding::as_yaml(&self)?;
-        if friendly_names {
-            // Best-effort attempt to post-process comments on inventory.
-            InventoryAliasCommentPostProcessor::try_init(opts)
-                .await?
-                .post_process(&mut data)?;
-        }
-        if timestamp {
-            TimestampPostProcessor.post_process(&mut data)?;
+        let mut sorted = self.clone();
+        sorted.sort();
+        let data = format.encode(&sorted)?;
+        post_process(data, opts, format, friendly_names, timestamp).await
+    }
+
+    /// Expand the environment variables and `{hostname}` placeholders in
+    /// every zone and record. Zones which expand to the same zone are merged.
+    pub fn expand(&self) -> Result<Self> {
+        let mut expanded = InventoryData(None);
+        for (zone, records) in self.records() {
+            let zone = crate::util::template::expand(&zone)?;
+            let entry = expanded
+                .0
+                .get_or_insert(IndexMap::new())
+                .entry(zone.clone())
+                .or_insert_with(|| InventoryZone(None));
+            for record in records {
+                let record = record
+                    .expand()
+                    .with_context(|| format!("in zone '{zone}'"))?;
+                entry.0.get_or_insert(IndexSet::new()).insert(record);
+            }
         }
-        Ok(data)
+        Ok(expanded)
     }
 
     /// Returns whether a record exists in the inventory data.
@@ -97,57 +860,129 @@ impl InventoryData {
         record_id: impl Into<String>,
     ) -> bool {
         let zone_id = zone_id.into();
-        let record_id = InventoryRecord(record_id.into());
+        let record_id = record_id.into();
 
         // Magic that checks whether the record exists
         self.0
             .as_ref()
             .and_then(|map| map.get(&zone_id))
             .and_then(|zone| zone.0.as_ref())
-            .map(|records| records.contains(&record_id))
+            .map(|records| records.iter().any(|r| r.is(&record_id)))
             .unwrap_or(false)
     }
 
-    /// Insert a record into the inventory data.
+    /// Returns whether a record model, compared as a whole, exists in the
+    /// inventory data.
+    pub fn contains_record(
+        &self,
+        zone_id: impl Into<String>,
+        record: &InventoryRecord,
+    ) -> bool {
+        self.0
+            .as_ref()
+            .and_then(|map| map.get(&zone_id.into()))
+            .and_then(|zone| zone.0.as_ref())
+            .is_some_and(|records| records.contains(record))
+    }
+
+    /// Insert a record into the inventory data, unless it is already present.
     pub fn insert(
         &mut self,
         zone_id: impl Into<String>,
         record_id: impl Into<String>,
+    ) {
+        let (zone_id, record_id) = (zone_id.into(), record_id.into());
+        if !self.contains(&zone_id, &record_id) {
+            self.insert_record(zone_id, InventoryRecord::Plain(record_id));
+        }
+    }
+
+    /// Insert a record model into the inventory data.
+    pub fn insert_record(
+        &mut self,
+        zone_id: impl Into<String>,
+        record: InventoryRecord,
     ) {
         // Magic that inserts the record
         self.0
-            .get_or_insert(HashMap::new())
+            .get_or_insert(IndexMap::new())
             .entry(zone_id.into())
             .or_insert_with(|| InventoryZone(None))
             .0
-            .get_or_insert(HashSet::new())
-            .insert(InventoryRecord(record_id.into()));
+            .get_or_insert(IndexSet::new())
+            .insert(record);
     }
 
-    /// Remove a record from the inventory data. Returns whether the value was
-    /// present in the set.
-    pub fn remove(
+    /// Replace a record model, compared as a whole, keeping its position.
+    /// Returns whether the record was present.
+    pub fn replace(
         &mut self,
         zone_id: impl Into<String>,
-        record_id: impl Into<String>,
-    ) -> Result<bool> {
+        previous: &InventoryRecord,
+        record: InventoryRecord,
+    ) -> bool {
         let zone_id = zone_id.into();
-        let record_id = record_id.into();
+        let Some(records) = self
+            .0
+            .as_mut()
+            .and_then(|map| map.get_mut(&zone_id))
+            .and_then(|zone| zone.0.as_mut())
+        else {
+            return false;
+        };
+        let Some(idx) = records.get_index_of(previous) else {
+            return false;
+        };
+        let mut replaced = records.drain(..).collect::<Vec<InventoryRecord>>();
+        replaced[idx] = record;
+        records.extend(replaced);
+        true
+    }
 
-        let mut removed = false;
-        let mut prune = false; // whether to remove an empty zone container
+    /// Return the zones and record models, in order.
+    pub fn records(&self) -> Vec<(String, Vec<InventoryRecord>)> {
+        self.0
+            .iter()
+            .flatten()
+            .map(|(zone, records)| {
+                let records = records.0.iter().flatten().cloned().collect();
+                (zone.clone(), records)
+            })
+            .collect()
+    }
+
+    /// Sort the inventory data by zone, then record.
+    pub fn sort(&mut self) {
         if let Some(map) = self.0.as_mut() {
-            if let Some(zone) = map.get_mut(&zone_id) {
+            map.sort_keys();
+            for zone in map.values_mut() {
                 if let Some(records) = zone.0.as_mut() {
-                    removed = records.remove(&InventoryRecord(record_id));
-                    prune = records.is_empty();
+                    records.sort_by(|a, b| a.key().cmp(b.key()));
                 }
             }
-            if prune {
-                map.remove(&zone_id);
-            }
         }
-        Ok(removed)
+    }
+
+    /// Remove a record model, compared as a whole, from the inventory data.
+    /// Returns whether the record was present.
+    pub fn remove_record(
+        &mut self,
+        zone_id: impl Into<String>,
+        record: &InventoryRecord,
+    ) -> bool {
+        let zone_id = zone_id.into();
+        let Some(map) = self.0.as_mut() else {
+            return false;
+        };
+        let Some(records) = map.get_mut(&zone_id).and_then(|z| z.0.as_mut())
+        else {
+            return false;
+        };
+        let removed = records.shift_remove(record);
+        if records.is_empty() {
+            map.shift_remove(&zone_id);
+        }
+        removed
     }
 
     /// Returns whether the inventory data has no records
@@ -164,3 +999,224 @@ impl InventoryData {
             .is_some_and(|len| len > 0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(data: InventoryData) -> InventorySet {
+        let path = PathBuf::from("inventory.yaml");
+        InventorySet {
+            path: path.clone(),
+            fragments: vec![Inventory {
+                path,
+                data,
+                source: None,
+                format: InventoryFormat::Yaml,
+                expanded: false,
+            }],
+            modified: HashSet::new(),
+        }
+    }
+
+    fn record(id: &str, name: &str, record_type: &str) -> Record {
+        Record {
+            id: id.to_string(),
+            zone_id: "imbleau.com".to_string(),
+            zone_name: "imbleau.com".to_string(),
+            name: name.to_string(),
+            record_type: record_type.to_string(),
+            content: "1.2.3.4".to_string(),
+            locked: false,
+            ttl: 1,
+            proxied: false,
+        }
+    }
+
+    fn detailed(id: &str, name: &str, record_type: &str) -> InventoryRecord {
+        InventoryRecord::Detailed {
+            name: name.to_string(),
+            id: Some(id.to_string()),
+            record_type: Some(record_type.to_string()),
+            groups: vec![],
+        }
+    }
+
+    /// An inventory listing the same name as an A record, an AAAA record and
+    /// a plain entry.
+    fn dual_stack() -> Result<InventoryData> {
+        InventoryFormat::Yaml.decode(
+            b"
+imbleau.com:
+  - {name: www.imbleau.com, id: '1', type: A}
+  - {name: www.imbleau.com, id: '2', type: AAAA}
+  - www.imbleau.com
+",
+        )
+    }
+
+    #[test]
+    fn is_matches_names_and_ids() {
+        let linked = detailed("1", "www.imbleau.com", "A");
+        assert!(linked.is("www.imbleau.com"));
+        assert!(linked.is("1"));
+        assert!(!linked.is("2"));
+        assert!(InventoryRecord::Plain("1".to_string()).is("1"));
+        let glob = InventoryRecord::Glob {
+            glob: "www.imbleau.com".to_string(),
+            groups: vec![],
+        };
+        assert!(!glob.is("www.imbleau.com"));
+    }
+
+    #[test]
+    fn relinks_only_the_record_whose_id_changed() {
+        // The AAAA record was re-created with a new id
+        let records = [
+            record("1", "www.imbleau.com", "A"),
+            record("3", "www.imbleau.com", "AAAA"),
+        ];
+        let v4 = detailed("1", "www.imbleau.com", "A");
+        let v6 = detailed("2", "www.imbleau.com", "AAAA");
+
+        let refers = |listed: &InventoryRecord| {
+            records.iter().find(|r| listed.refers_to(r)).map(|r| &r.id)
+        };
+        let relinks = |listed: &InventoryRecord| {
+            records.iter().find(|r| listed.relinks_to(r)).map(|r| &r.id)
+        };
+        assert_eq!(refers(&v4).map(String::as_str), Some("1"));
+        assert_eq!(refers(&v6), None);
+        assert_eq!(relinks(&v6).map(String::as_str), Some("3"));
+        assert_eq!(
+            v6.link(&records[1]),
+            detailed("3", "www.imbleau.com", "AAAA")
+        );
+
+        // Plain and unlinked entries are never re-linked
+        let plain = InventoryRecord::Plain("www.imbleau.com".to_string());
+        assert!(plain.refers_to(&records[0]));
+        assert!(!plain.relinks_to(&records[0]));
+    }
+
+    #[test]
+    fn replace_rewrites_only_that_entry() -> Result<()> {
+        let mut set = set(dual_stack()?);
+        let stale = detailed("2", "www.imbleau.com", "AAAA");
+        let relinked = detailed("3", "www.imbleau.com", "AAAA");
+        assert!(set.replace("imbleau.com", &stale, relinked.clone()));
+        assert!(set.modified.contains(&0));
+
+        let plain = InventoryRecord::Plain("www.imbleau.com".to_string());
+        let records = set.data().records();
+        assert_eq!(
+            records[0].1,
+            vec![detailed("1", "www.imbleau.com", "A"), relinked, plain]
+        );
+
+        // The stale record is no longer listed as a whole
+        assert!(!set.replace("imbleau.com", &stale, stale.clone()));
+        Ok(())
+    }
+
+    #[test]
+    fn remove_record_prunes_only_that_entry() -> Result<()> {
+        let mut set = set(dual_stack()?);
+        let stale = detailed("2", "www.imbleau.com", "AAAA");
+        assert!(set.remove_record("imbleau.com", &stale));
+        assert!(set.modified.contains(&0));
+
+        let plain = InventoryRecord::Plain("www.imbleau.com".to_string());
+        let records = set.data().records();
+        assert_eq!(
+            records[0].1,
+            vec![detailed("1", "www.imbleau.com", "A"), plain]
+        );
+        assert!(!set.remove_record("imbleau.com", &stale));
+        Ok(())
+    }
+
+    #[test]
+    fn set_data_keeps_patterns() -> Result<()> {
+        let data = InventoryFormat::Yaml.decode(
+            br"
+imbleau.com:
+  - www.imbleau.com
+  - {glob: '*.svc.imbleau.com'}
+  - {regex: 'web-\d+\.imbleau\.com', groups: [web]}
+",
+        )?;
+        let mut set = set(data.clone());
+
+        // Unchanged data is left as is
+        set.set_data(&data);
+        assert_eq!(set.fragments[0].data, data);
+        assert!(set.modified.is_empty());
+
+        // Removing a record keeps the patterns unchanged
+        let mut wanted = data.clone();
+        let www = InventoryRecord::Plain("www.imbleau.com".to_string());
+        wanted.remove_record("imbleau.com", &www);
+        set.set_data(&wanted);
+        assert_eq!(set.fragments[0].data, wanted);
+        assert!(set.fragments[0]
+            .data
+            .records()
+            .iter()
+            .flat_map(|(_, records)| records)
+            .all(InventoryRecord::is_pattern));
+
+        // Patterns are removed as a whole
+        let glob = InventoryRecord::Glob {
+            glob: "*.svc.imbleau.com".to_string(),
+            groups: vec![],
+        };
+        wanted.remove_record("imbleau.com", &glob);
+        set.set_data(&wanted);
+        assert_eq!(set.fragments[0].data, wanted);
+        assert!(!set.fragments[0].data.contains_record("imbleau.com", &glob));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn saves_changes_around_templates() -> Result<()> {
+        let source = "\
+# sites
+imbleau.com:
+  - www.imbleau.com
+  - api.imbleau.com
+  - 'api.${CDDNS_TEST_UNSET:-dev}.imbleau.com'
+'${CDDNS_TEST_UNSET:-example.com}':
+  - example.com
+";
+        let mut inventory = Inventory::builder()
+            .path("inventory.yaml")
+            .with_bytes(source.as_bytes())?
+            .build()?;
+        inventory.data = inventory.data.expand()?;
+        inventory.expanded = true;
+
+        // Remove a plain and a templated entry, and add one
+        for name in ["www.imbleau.com", "api.dev.imbleau.com"] {
+            let record = InventoryRecord::Plain(name.to_string());
+            inventory.data.remove_record("imbleau.com", &record);
+        }
+        inventory.data.insert("example.com", "new.example.com");
+        let contents = inventory
+            .to_string(&ConfigOpts::default(), false, false)
+            .await?;
+        assert_eq!(
+            contents,
+            "\
+# sites
+imbleau.com:
+  - api.imbleau.com
+  - 'api.${CDDNS_TEST_UNSET:-dev}.imbleau.com'
+'${CDDNS_TEST_UNSET:-example.com}':
+  - example.com
+  - new.example.com
+"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+use crate::cloudflare::models::Record;
+use ansi_term::Colour;
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+/// The version of the plan file format.
+const PLAN_VERSION: u32 = 1;
+
+/// A reviewed set of record changes, saved by `inventory plan` and executed
+/// by `inventory apply`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Plan {
+    pub version: u32,
+    /// When the plan was made, in RFC 3339 format.
+    pub created: String,
+    pub changes: Vec<PlannedChange>,
+}
+
+/// A change to a single Cloudflare record, with the state it was planned
+/// from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedChange {
+    pub zone_id: String,
+    pub zone_name: String,
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub before: RecordState,
+    pub after: RecordState,
+}
+
+/// The fields of a record which a plan may change.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordState {
+    pub content: String,
+    /// The TTL in seconds, where 1 is automatic.
+    pub ttl: u32,
+    pub proxied: bool,
+}
+
+impl From<&Record> for RecordState {
+    fn from(record: &Record) -> Self {
+        Self {
+            content: record.content.clone(),
+            ttl: record.ttl,
+            proxied: record.proxied,
+        }
+    }
+}
+
+impl Plan {
+    /// Make a new plan from a list of changes.
+    pub fn new(changes: Vec<PlannedChange>) -> Self {
+        Self {
+            version: PLAN_VERSION,
+            created: Local::now().to_rfc3339(),
+            changes,
+        }
+    }
+
+    /// Read a plan from a file.
+    pub async fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("reading plan '{}'", path.display()))?;
+        let plan: Plan =
+            serde_json::from_str(&contents).with_context(|| {
+                format!("deserializing plan '{}'", path.display())
+            })?;
+        if plan.version != PLAN_VERSION {
+            bail!(
+                "unsupported plan version {}, expected {PLAN_VERSION}",
+                plan.version
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Save the plan to a file, overwriting if necessary. Plans change no
+    /// records, so they are saved in a dry run too.
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let contents = crate::util::encoding::as_json(self)? + "\n";
+        crate::util::fs::save(path, contents, 0, false).await?;
+        Ok(())
+    }
+
+    /// Return the changes whose record drifted since the plan was made, with
+    /// how it drifted.
+    pub fn drifted(&self, records: &[Record]) -> Vec<(&PlannedChange, String)> {
+        self.changes
+            .iter()
+            .filter_map(|change| Some((change, change.drift(records)?)))
+            .collect()
+    }
+
+    /// Return the plan as a diff per record, with a summary.
+    pub fn diff(&self, color: bool) -> String {
+        let mut diff = String::new();
+        for change in self.changes.iter() {
+            let _ = writeln!(diff, "{}", change.diff(color));
+        }
+        let _ = write!(diff, "Plan: {} to update.", self.changes.len());
+        diff
+    }
+}
+
+impl PlannedChange {
+    /// Plan to point a record to new content.
+    pub fn update(record: &Record, content: impl Into<String>) -> Self {
+        let before = RecordState::from(record);
+        let after = RecordState {
+            content: content.into(),
+            ..before.clone()
+        };
+        Self {
+            zone_id: record.zone_id.clone(),
+            zone_name: record.zone_name.clone(),
+            id: record.id.clone(),
+            name: record.name.clone(),
+            record_type: record.record_type.clone(),
+            before,
+            after,
+        }
+    }
+
+    /// Return the changed fields, as a body for the Cloudflare API.
+    pub fn patch(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut patch = serde_json::Map::new();
+        if self.before.content != self.after.content {
+            patch.insert("content".into(), self.after.content.clone().into());
+        }
+        if self.before.ttl != self.after.ttl {
+            patch.insert("ttl".into(), self.after.ttl.into());
+        }
+        if self.before.proxied != self.after.proxied {
+            patch.insert("proxied".into(), self.after.proxied.into());
+        }
+        patch
+    }
+
+    /// Return how the remote record drifted from the state the change was
+    /// planned from, if it did.
+    pub fn drift(&self, records: &[Record]) -> Option<String> {
+        let Some(record) = records
+            .iter()
+            .find(|r| r.zone_id == self.zone_id && r.id == self.id)
+        else {
+            return Some("record no longer exists".to_string());
+        };
+        let current = RecordState::from(record);
+        let mut drifted = vec![];
+        if current.content != self.before.content {
+            drifted.push(format!(
+                "content is '{}', planned from '{}'",
+                current.content, self.before.content
+            ));
+        }
+        if current.ttl != self.before.ttl {
+            drifted.push(format!(
+                "ttl is {}, planned from {}",
+                ttl(current.ttl),
+                ttl(self.before.ttl)
+            ));
+        }
+        if current.proxied != self.before.proxied {
+            drifted.push(format!(
+                "proxied is {}, planned from {}",
+                current.proxied, self.before.proxied
+            ));
+        }
+        match drifted.is_empty() {
+            true => None,
+            false => Some(drifted.join(", ")),
+        }
+    }
+
+    /// Return the change as a diff of the fields which change.
+    pub fn diff(&self, color: bool) -> String {
+        let paint = |colour: Colour, text: String| match color {
+            true => colour.paint(text).to_string(),
+            false => text,
+        };
+        let mut diff = format!(
+            "{} {} ({}, {})",
+            paint(Colour::Yellow, "~".to_string()),
+            self.name,
+            self.record_type,
+            self.id
+        );
+        let mut field = |name: &str, before: String, after: String| {
+            if before != after {
+                let _ = write!(
+                    diff,
+                    "\n    {name}: {} → {}",
+                    paint(Colour::Red, before),
+                    paint(Colour::Green, after)
+                );
+            }
+        };
+        field(
+            "content",
+            self.before.content.clone(),
+            self.after.content.clone(),
+        );
+        field("ttl", ttl(self.before.ttl), ttl(self.after.ttl));
+        field(
+            "proxied",
+            self.before.proxied.to_string(),
+            self.after.proxied.to_string(),
+        );
+        diff
+    }
+}
+
+/// Return a TTL for display, where 1 is automatic.
+fn ttl(ttl: u32) -> String {
+    match ttl {
+        1 => "auto".to_string(),
+        ttl => ttl.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(content: &str, ttl: u32, proxied: bool) -> Record {
+        Record {
+            id: "1".to_string(),
+            zone_id: "z".to_string(),
+            zone_name: "imbleau.com".to_string(),
+            name: "www.imbleau.com".to_string(),
+            record_type: "A".to_string(),
+            content: content.to_string(),
+            locked: false,
+            ttl,
+            proxied,
+        }
+    }
+
+    #[test]
+    fn patch_holds_only_changed_fields() {
+        let change =
+            PlannedChange::update(&record("1.2.3.4", 1, true), "5.6.7.8");
+        let patch = change.patch();
+        assert_eq!(patch.len(), 1);
+        assert_eq!(patch.get("content"), Some(&"5.6.7.8".into()));
+
+        let mut change = change;
+        change.after.content = change.before.content.clone();
+        change.after.ttl = 300;
+        let patch = change.patch();
+        assert_eq!(patch.len(), 1);
+        assert_eq!(patch.get("ttl"), Some(&300.into()));
+    }
+
+    #[test]
+    fn reports_drifted_records() {
+        let change =
+            PlannedChange::update(&record("1.2.3.4", 1, false), "5.6.7.8");
+        assert_eq!(change.drift(&[record("1.2.3.4", 1, false)]), None);
+        assert_eq!(
+            change.drift(&[record("9.9.9.9", 300, false)]).as_deref(),
+            Some("content is '9.9.9.9', planned from '1.2.3.4', ttl is 300, planned from auto")
+        );
+        assert_eq!(
+            change.drift(&[record("1.2.3.4", 1, true)]).as_deref(),
+            Some("proxied is true, planned from false")
+        );
+    }
+
+    #[test]
+    fn reports_deleted_records() {
+        let change =
+            PlannedChange::update(&record("1.2.3.4", 1, false), "5.6.7.8");
+        let drift = Some("record no longer exists".to_string());
+        assert_eq!(change.drift(&[]), drift);
+
+        // A record with the same id in another zone is another record
+        let mut moved = record("1.2.3.4", 1, false);
+        moved.zone_id = "other".to_string();
+        assert_eq!(change.drift(&[moved]), drift);
+    }
+
+    #[test]
+    fn refuses_plans_with_drifted_records() {
+        let unchanged = record("1.2.3.4", 1, false);
+        let mut deleted = record("1.2.3.4", 1, false);
+        deleted.id = "2".to_string();
+        let plan = Plan::new(vec![
+            PlannedChange::update(&unchanged, "5.6.7.8"),
+            PlannedChange::update(&deleted, "5.6.7.8"),
+        ]);
+        let drifted = plan
+            .drifted(std::slice::from_ref(&unchanged))
+            .into_iter()
+            .map(|(change, drift)| (change.id.as_str(), drift))
+            .collect::<Vec<_>>();
+        assert_eq!(drifted, vec![("2", "record no longer exists".to_string())]);
+        assert!(plan.drifted(&[unchanged, deleted]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn reads_only_supported_versions() -> Result<()> {
+        let dir = std::env::temp_dir()
+            .join(format!("cddns-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("plan.json");
+        let change =
+            PlannedChange::update(&record("1.2.3.4", 1, false), "5.6.7.8");
+        let mut plan = Plan::new(vec![change]);
+        plan.save(&path).await?;
+        let read = Plan::from_file(&path).await;
+
+        plan.version = PLAN_VERSION + 1;
+        plan.save(&path).await?;
+        let unsupported = Plan::from_file(&path).await;
+        std::fs::remove_dir_all(&dir)?;
+
+        let read = read?;
+        assert_eq!(read.version, PLAN_VERSION);
+        assert_eq!(read.changes.len(), 1);
+        assert_eq!(read.changes[0].after.content, "5.6.7.8");
+        let err = unsupported.err().map(|err| err.to_string());
+        assert_eq!(
+            err.as_deref(),
+            Some("unsupported plan version 2, expected 1")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use crate::cloudflare::models::Record;
+use crate::inventory::models::InventoryRecord;
+use crate::util::exit::{ExitCode, ExitError};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The formats a report can be printed as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    Json,
+    Yaml,
+}
+
+impl ReportFormat {
+    /// Serialize a report in this format.
+    pub fn encode(&self, report: &Report) -> Result<String> {
+        match self {
+            Self::Json => crate::util::encoding::as_json(report),
+            Self::Yaml => crate::util::encoding::as_yaml(report),
+        }
+    }
+}
+
+/// The outcome of `inventory check`, `update` or `prune`. Every list is
+/// always present, so the schema is stable across commands.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Report {
+    /// Records pointing to the public IP.
+    pub valid: Vec<ReportRecord>,
+    /// Records pointing to a stale IP, when checked.
+    pub outdated: Vec<ReportRecord>,
+    /// Inventory entries which match no record.
+    pub invalid: Vec<ReportEntry>,
+    /// Outdated records which were updated.
+    pub updated: Vec<ReportRecord>,
+    /// Invalid entries which were pruned from the inventory.
+    pub pruned: Vec<ReportEntry>,
+    /// Records and entries which failed to update or prune.
+    pub failed: Vec<ReportFailure>,
+}
+
+/// A Cloudflare record, with the public IP it is expected to point to.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportRecord {
+    pub zone_id: String,
+    pub zone_name: String,
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    /// The resolved public IP, if it could be resolved.
+    pub ip: Option<String>,
+}
+
+/// An inventory entry, by the zone and record it is listed as.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportEntry {
+    pub zone: String,
+    pub record: String,
+}
+
+/// A record or entry which failed to update or prune.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportFailure {
+    pub zone: String,
+    pub record: String,
+    pub error: String,
+}
+
+impl Report {
+    /// Make a report of checked records, given the public IP each record is
+    /// expected to point to, by record id.
+    pub fn new(
+        valid: &[Record],
+        outdated: &[Record],
+        invalid: &[(String, InventoryRecord)],
+        ips: &HashMap<String, String>,
+    ) -> Self {
+        let records = |records: &[Record]| {
+            records
+                .iter()
+                .map(|record| ReportRecord::new(record, ips.get(&record.id)))
+                .collect()
+        };
+        Self {
+            valid: records(valid),
+            outdated: records(outdated),
+            invalid: invalid
+                .iter()
+                .map(|(zone, record)| ReportEntry {
+                    zone: zone.clone(),
+                    record: record.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Return the exit code for the report. Failures take precedence over
+    /// outdated or invalid records which remain.
+    pub fn exit_code(&self) -> ExitCode {
+        if !self.failed.is_empty() {
+            ExitCode::PartialFailure
+        } else if self.remaining_outdated() > 0 || self.remaining_invalid() > 0
+        {
+            ExitCode::Drift
+        } else {
+            ExitCode::Ok
+        }
+    }
+
+    /// Return an error for the exit code of the report, unless all is well.
+    pub fn exit_error(&self) -> Option<ExitError> {
+        let message = match self.exit_code() {
+            ExitCode::Ok | ExitCode::Fatal => return None,
+            ExitCode::Drift => format!(
+                "drift found, {} outdated and {} invalid records remain",
+                self.remaining_outdated(),
+                self.remaining_invalid()
+            ),
+            ExitCode::PartialFailure => {
+                format!("{} changes failed", self.failed.len())
+            }
+        };
+        Some(ExitError::new(self.exit_code(), message))
+    }
+
+    /// Return the amount of outdated records which were not updated.
+    fn remaining_outdated(&self) -> usize {
+        self.outdated
+            .iter()
+            .filter(|r| !self.updated.iter().any(|u| u.id == r.id))
+            .count()
+    }
+
+    /// Return the amount of invalid entries which were not pruned.
+    fn remaining_invalid(&self) -> usize {
+        self.invalid
+            .iter()
+            .filter(|e| {
+                !self
+                    .pruned
+                    .iter()
+                    .any(|p| p.zone == e.zone && p.record == e.record)
+            })
+            .count()
+    }
+}
+
+impl ReportRecord {
+    /// Describe a record with the public IP it is expected to point to.
+    pub fn new(record: &Record, ip: Option<&String>) -> Self {
+        Self {
+            zone_id: record.zone_id.clone(),
+            zone_name: record.zone_name.clone(),
+            id: record.id.clone(),
+            name: record.name.clone(),
+            record_type: record.record_type.clone(),
+            content: record.content.clone(),
+            ip: ip.cloned(),
+        }
+    }
+}
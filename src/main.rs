@@ -3,7 +3,6 @@
 #![deny(clippy::expect_used)] // use context/with_context
 // Features
 #![feature(slice_pattern)]
-#![feature(try_blocks)]
 #![feature(unwrap_infallible)]
 #![feature(iter_intersperse)]
 #![feature(exact_size_is_empty)]
@@ -13,10 +12,12 @@
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+use config::layers::ConfigLayers;
 use config::models::ConfigOpts;
 use std::path::PathBuf;
-use tracing::{error, Level};
+use tracing::{error, warn, Level};
 use tracing_subscriber::prelude::*;
+use util::exit::{ExitCode, ExitError};
 mod cloudflare;
 mod cmd;
 mod config;
@@ -32,34 +33,52 @@ struct Args {
     /// A config file to use. [default: $XDG_CONFIG_HOME/cddns/config.toml]
     #[clap(short, long, env = "CDDNS_CONFIG", value_name = "file")]
     pub config: Option<PathBuf>,
+    /// A profile of the config file to use, overlaying its base options.
+    #[clap(short, long, env = "CDDNS_PROFILE", value_name = "name")]
+    pub profile: Option<String>,
     /// Enable verbose logging.
     #[clap(short)]
     pub v: bool,
     /// Your Cloudflare API key token.
     #[clap(short, long, value_name = "token")]
     pub token: Option<String>,
+    /// Log changes to Cloudflare records and files, without making them.
+    #[clap(long, global = true)]
+    pub dry_run: bool,
 }
 
 impl Args {
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn run(self) -> Result<()> {
         // Apply CLI configuration layering
-        let default_cfg = ConfigOpts::default();
-        let toml_cfg = ConfigOpts::from_file(self.config)?;
-        let env_cfg = ConfigOpts::from_env()?;
-        let cli_cfg = ConfigOpts::builder().verify_token(self.token).build();
-        let opts = ConfigOpts::builder()
-            .merge(default_cfg)
-            .merge(toml_cfg)
-            .merge(env_cfg)
-            .merge(cli_cfg)
+        // A profile being built or set need not exist yet
+        let profile = match &self.action {
+            Subcommands::Config(inner) if inner.creates_profile() => None,
+            _ => self.profile.clone(),
+        };
+        let cli = ConfigOpts::builder()
+            .verify_token(self.token)
+            .inventory_dry_run(self.dry_run.then_some(true))
             .build();
+        let layers = match &self.action {
+            // Problems with the config file are reported by the check
+            Subcommands::Config(inner) if inner.checks_config() => {
+                ConfigLayers::load_lenient(self.config.clone(), profile, cli)?
+            }
+            _ => ConfigLayers::load(self.config.clone(), profile, cli)?,
+        };
+        let mut opts = layers.merge();
+        if !matches!(self.action, Subcommands::Config(_)) {
+            opts.verify.resolve_token()?;
+        }
 
         match self.action {
-            Subcommands::Config(inner) => inner.run(opts).await,
+            Subcommands::Config(inner) => {
+                inner.run(opts, layers, self.config, self.profile).await
+            }
             Subcommands::Verify(inner) => inner.run(opts).await,
             Subcommands::List(inner) => inner.run(opts).await,
-            Subcommands::Inventory(inner) => inner.run(opts).await,
+            Subcommands::Inventory(inner) => inner.run(opts, self.config).await,
         }
     }
 }
@@ -108,6 +127,8 @@ async fn main() -> Result<()> {
         // Format tracing
         .with(
             tracing_subscriber::fmt::layer()
+                // Keep stdout for output, such as reports
+                .with_writer(std::io::stderr)
                 .with_target(false)
                 .with_level(true)
                 .compact(),
@@ -117,6 +138,14 @@ async fn main() -> Result<()> {
         .context("error initializing logging")?;
 
     if let Err(err) = args.run().await {
+        // Drift and partial failures have their own exit codes
+        if let Some(exit) = err.downcast_ref::<ExitError>() {
+            match exit.code {
+                ExitCode::Drift => warn!("{exit}"),
+                _ => error!("{exit}"),
+            }
+            std::process::exit(exit.code as i32);
+        }
         if verbose {
             error!("{err:?}");
         } else {
@@ -124,7 +153,7 @@ async fn main() -> Result<()> {
                 "{err}\n\nEnable verbose logging (-v) for the full stack trace."
             );
         }
-        std::process::exit(1);
+        std::process::exit(ExitCode::Fatal as i32);
     }
     Ok(())
 }
@@ -15,3 +15,11 @@ where
 {
     serde_yaml::to_string(&contents).context("encoding as YAML")
 }
+
+/// Serialize an object to pretty-printed JSON.
+pub fn as_json<T>(contents: &T) -> Result<String>
+where
+    T: ?Sized + serde::Serialize,
+{
+    serde_json::to_string_pretty(&contents).context("encoding as JSON")
+}
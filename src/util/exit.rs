@@ -0,0 +1,41 @@
+use std::fmt::{self, Display};
+
+/// The exit codes of the process, for scripts and monitoring.
+///
+/// Usage errors exit with 2, as reported by clap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Everything is as expected.
+    Ok = 0,
+    /// The command could not complete.
+    Fatal = 1,
+    /// Records are outdated or invalid, and were left that way.
+    Drift = 3,
+    /// Some changes were made, but others failed.
+    PartialFailure = 4,
+}
+
+/// An error which exits with a specific code, e.g. when a check finds drift.
+#[derive(Debug)]
+pub struct ExitError {
+    pub code: ExitCode,
+    pub message: String,
+}
+
+impl ExitError {
+    /// Create an error which exits with a code.
+    pub fn new(code: ExitCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ExitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ExitError {}
@@ -1,7 +1,23 @@
 use crate::util::scanner::prompt_yes_or_no;
 use anyhow::{bail, Context, Result};
-use std::path::Path;
-use tracing::debug;
+use chrono::Local;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
+
+/// Options to restore a file from a backup.
+#[derive(Debug, Clone, clap::Args)]
+pub struct RestoreOpts {
+    /// List available backups, newest first, instead of restoring.
+    #[clap(long)]
+    pub list: bool,
+    /// The backup to restore, by path or number (1 is the newest).
+    #[clap(value_name = "backup", default_value = "1")]
+    pub backup: String,
+}
+
+/// The file extension of backups.
+const BACKUP_EXTENSION: &str = "bak";
 
 /// If a file exists, remove it by force without user interaction.
 pub async fn remove_force(path: impl AsRef<Path>) -> Result<()> {
@@ -12,43 +28,357 @@ pub async fn remove_force(path: impl AsRef<Path>) -> Result<()> {
     Ok(())
 }
 
-/// If a file exists, remove it only after user grants permission.
-pub async fn remove_interactive(path: impl AsRef<Path>) -> Result<()> {
+/// If a file exists, continue only after user grants permission to overwrite.
+pub async fn overwrite_interactive(path: impl AsRef<Path>) -> Result<()> {
     let path = path.as_ref();
     if path.exists() {
         let overwrite = prompt_yes_or_no(
-            format!("Path '{}' exists, remove?", path.display()),
+            format!("Path '{}' exists, overwrite?", path.display()),
             "y/N",
         )?
         .unwrap_or(false);
-        if overwrite {
-            remove_force(path).await?;
-        } else {
+        if !overwrite {
             bail!("aborted")
         }
     }
     Ok(())
 }
 
-/// Save the desired contents, overwriting and creating directories if
-/// necessary.
+/// Save the desired contents atomically, creating directories if necessary.
+///
+/// The contents are written to a temporary file in the same directory, synced
+/// to disk, and renamed over the destination, keeping its permissions. If the
+/// destination is a symbolic link, the file it points to is replaced instead.
+/// If the destination exists, up to `backups` timestamped backups of it are
+/// kept. In a dry run, the write is only logged. Returns whether the file was
+/// written.
 pub async fn save(
     path: impl AsRef<Path>,
     contents: impl AsRef<[u8]>,
-) -> Result<()> {
+    backups: usize,
+    dry_run: bool,
+) -> Result<bool> {
     let path = path.as_ref();
-    if let Some(parent) = path.parent() {
-        tokio::fs::create_dir_all(parent).await.with_context(|| {
-            format!("unable to make directory '{}'", parent.display())
-        })?;
+    if dry_run {
+        info!(
+            bytes = contents.as_ref().len(),
+            "dry run: would write '{}'",
+            path.display()
+        );
+        return Ok(false);
     }
-    if path.exists() {
-        debug!("overwriting '{}'...", path.display());
-        remove_force(path).await?;
+    let path = &resolve_links(path);
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+        _ => PathBuf::from("."),
+    };
+    tokio::fs::create_dir_all(&parent).await.with_context(|| {
+        format!("unable to make directory '{}'", parent.display())
+    })?;
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("invalid file path '{}'", path.display()))?
+        .to_string_lossy();
+
+    // Keep the permissions and a backup of the existing file
+    let permissions = match tokio::fs::metadata(path).await {
+        Ok(metadata) => {
+            debug!("overwriting '{}'...", path.display());
+            if backups > 0 {
+                backup(path, backups).await?;
+            }
+            Some(metadata.permissions())
+        }
+        Err(_) => None,
+    };
+
+    // Write and sync a temporary file
+    let tmp_path =
+        parent.join(format!(".{file_name}.{}.tmp", std::process::id()));
+    let written: Result<()> = async {
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(contents.as_ref()).await?;
+        file.sync_all().await?;
+        if let Some(permissions) = permissions {
+            tokio::fs::set_permissions(&tmp_path, permissions).await?;
+        }
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+    .await;
+    if let Err(err) = written {
+        let _ = remove_force(&tmp_path).await;
+        return Err(err).with_context(|| {
+            format!("unable to write to '{}'", path.display())
+        });
+    }
+
+    // Persist the rename
+    #[cfg(unix)]
+    if let Ok(dir) = tokio::fs::File::open(&parent).await {
+        let _ = dir.sync_all().await;
     }
-    tokio::fs::write(path, contents)
-        .await
-        .with_context(|| format!("unable to write to '{}'", path.display()))?;
     debug!("wrote: '{}'", path.display());
+    Ok(true)
+}
+
+/// Resolve a symbolic link to the file it points to, so that writes replace
+/// the file rather than the link.
+fn resolve_links(path: &Path) -> PathBuf {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Copy a file to a timestamped backup beside it, removing all but the newest
+/// `keep` backups. Returns the path of the new backup.
+pub async fn backup(path: impl AsRef<Path>, keep: usize) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let timestamp = Local::now().format("%Y%m%dT%H%M%S%.3f");
+    let backup_path = PathBuf::from(format!(
+        "{}.{timestamp}.{BACKUP_EXTENSION}",
+        path.display()
+    ));
+    tokio::fs::copy(path, &backup_path)
+        .await
+        .with_context(|| format!("unable to back up '{}'", path.display()))?;
+    debug!("backed up: '{}'", backup_path.display());
+
+    for old in backups(path).await?.into_iter().skip(keep) {
+        remove_force(&old).await?;
+    }
+    Ok(backup_path)
+}
+
+/// Return the backups of a file, newest first.
+pub async fn backups(path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let path = &resolve_links(path.as_ref());
+    let Some(file_name) = path.file_name() else {
+        return Ok(vec![]);
+    };
+    let prefix = format!("{}.", file_name.to_string_lossy());
+    let suffix = format!(".{BACKUP_EXTENSION}");
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut backups = vec![];
+    let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+        return Ok(backups);
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_backup = name
+            .strip_prefix(&prefix)
+            .and_then(|name| name.strip_suffix(&suffix))
+            .is_some_and(|timestamp| {
+                timestamp.starts_with(|c: char| c.is_ascii_digit())
+                    && timestamp
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || c == 'T' || c == '.')
+            });
+        if is_backup {
+            backups.push(path.with_file_name(entry.file_name()));
+        }
+    }
+    // Timestamps sort lexicographically
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+/// Restore a file from one of its backups, backing up the current file first
+/// and keeping up to `backups` backups. In a dry run, the write is only
+/// logged.
+pub async fn restore(
+    path: &Path,
+    cli_opts: &RestoreOpts,
+    backups: usize,
+    dry_run: bool,
+) -> Result<()> {
+    let available = self::backups(path).await?;
+    if cli_opts.list {
+        if available.is_empty() {
+            warn!("no backups found for '{}'", path.display());
+        }
+        for (i, backup) in available.iter().enumerate() {
+            println!("[{}] {}", i + 1, backup.display());
+        }
+        return Ok(());
+    }
+
+    let backup = select_backup(path, &available, &cli_opts.backup)?;
+    let contents = tokio::fs::read(&backup)
+        .await
+        .with_context(|| format!("reading backup '{}'", backup.display()))?;
+    let confirm = prompt_yes_or_no(
+        format!("Restore '{}' from '{}'?", path.display(), backup.display()),
+        "Y/n",
+    )?
+    .unwrap_or(true);
+    if !confirm {
+        bail!("aborted");
+    }
+    if save(path, contents, backups, dry_run).await? {
+        info!("restored '{}' from '{}'", path.display(), backup.display());
+    }
     Ok(())
 }
+
+/// Select a backup of a file by path, or by number among the available
+/// backups, where 1 is the newest.
+fn select_backup(
+    path: &Path,
+    available: &[PathBuf],
+    backup: &str,
+) -> Result<PathBuf> {
+    match backup.parse::<usize>() {
+        Ok(idx) => available
+            .get(idx.wrapping_sub(1))
+            .with_context(|| {
+                format!("no backup [{idx}] found for '{}'", path.display())
+            })
+            .cloned(),
+        Err(_) => Ok(PathBuf::from(backup)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> Result<PathBuf> {
+        let dir = std::env::temp_dir()
+            .join(format!("cddns-fs-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Save each version of a file in turn, so every backup has its own
+    /// timestamp.
+    async fn save_versions(
+        path: &Path,
+        versions: &[&str],
+        backups: usize,
+    ) -> Result<()> {
+        for contents in versions {
+            save(path, contents, backups, false).await?;
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        Ok(())
+    }
+
+    async fn read_all(paths: &[PathBuf]) -> Result<Vec<String>> {
+        let mut contents = vec![];
+        for path in paths {
+            contents.push(tokio::fs::read_to_string(path).await?);
+        }
+        Ok(contents)
+    }
+
+    #[tokio::test]
+    async fn keeps_only_the_newest_backups() -> Result<()> {
+        let dir = temp_dir("backups")?;
+        let path = dir.join("config.toml");
+        let saved = save_versions(&path, &["1", "2", "3", "4"], 2).await;
+        let kept = backups(&path).await;
+        let contents = match kept.as_ref() {
+            Ok(kept) => read_all(kept).await,
+            Err(_) => Ok(vec![]),
+        };
+        let current = tokio::fs::read_to_string(&path).await;
+        std::fs::remove_dir_all(&dir)?;
+        saved?;
+
+        // Each save backs up the file it replaces, newest first
+        assert_eq!(kept?.len(), 2);
+        assert_eq!(contents?, vec!["3", "2"]);
+        assert_eq!(current?, "4");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dry_runs_and_zero_backups_write_no_backups() -> Result<()> {
+        let dir = temp_dir("no-backups")?;
+        let path = dir.join("config.toml");
+        let saved = save_versions(&path, &["1", "2"], 0).await;
+        let dry_run = save(&path, "3", 5, true).await;
+        let kept = backups(&path).await;
+        let current = tokio::fs::read_to_string(&path).await;
+        std::fs::remove_dir_all(&dir)?;
+        saved?;
+
+        assert!(!dry_run?);
+        assert!(kept?.is_empty());
+        assert_eq!(current?, "2");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn writes_through_symbolic_links() -> Result<()> {
+        let dir = temp_dir("links")?;
+        let target = dir.join("dotfiles").join("config.toml");
+        let link = dir.join("config.toml");
+        std::fs::create_dir_all(dir.join("dotfiles"))?;
+        std::fs::write(&target, "1")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+        let resolved = target.canonicalize()?;
+
+        let saved = save(&link, "2", 1, false).await;
+        let is_link = std::fs::symlink_metadata(&link)
+            .map(|metadata| metadata.file_type().is_symlink());
+        let current = tokio::fs::read_to_string(&target).await;
+        let kept = backups(&link).await;
+        std::fs::remove_dir_all(&dir)?;
+
+        // The link is kept, and the backup is made beside the target
+        assert!(saved?);
+        assert!(is_link?);
+        assert_eq!(current?, "2");
+        let kept = kept?;
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].parent(), resolved.parent());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn selects_backups_by_number_or_path() -> Result<()> {
+        let dir = temp_dir("restore")?;
+        let path = dir.join("config.toml");
+        let saved = save_versions(&path, &["1", "2", "3"], 5).await;
+        let available = backups(&path).await;
+        let contents = match available.as_ref() {
+            Ok(available) => read_all(available).await,
+            Err(_) => Ok(vec![]),
+        };
+        std::fs::remove_dir_all(&dir)?;
+        saved?;
+        let available = available?;
+        assert_eq!(contents?, vec!["2", "1"]);
+
+        // 1 is the newest backup
+        assert_eq!(select_backup(&path, &available, "1")?, available[0]);
+        assert_eq!(select_backup(&path, &available, "2")?, available[1]);
+        let by_path = available[1].to_string_lossy();
+        assert_eq!(select_backup(&path, &available, &by_path)?, available[1]);
+        for missing in ["0", "3"] {
+            let err = select_backup(&path, &available, missing)
+                .err()
+                .map(|err| err.to_string());
+            assert_eq!(
+                err,
+                Some(format!(
+                    "no backup [{missing}] found for '{}'",
+                    path.display()
+                ))
+            );
+        }
+        Ok(())
+    }
+}
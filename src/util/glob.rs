@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Returns whether a string contains glob wildcards.
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Compile a glob pattern to an anchored regex.
+///
+/// `*` matches within a path component, `**` matches across components, `?`
+/// matches a single character, and `[...]` matches a character class.
+pub fn to_regex(pattern: &str) -> Result<Regex> {
+    compile(pattern, '/', "^")
+}
+
+/// Compile a glob pattern for hostnames to an anchored, case-insensitive
+/// regex, where `*` matches within a label and `**` matches across labels.
+pub fn to_hostname_regex(pattern: &str) -> Result<Regex> {
+    compile(pattern, '.', "(?i)^")
+}
+
+/// Compile a glob pattern, where wildcards stop at a separator.
+fn compile(pattern: &str, separator: char, prefix: &str) -> Result<Regex> {
+    let escaped = regex::escape(&separator.to_string());
+    let mut regex = String::from(prefix);
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.next_if_eq(&'*').is_some() => {
+                // `**/` also matches no components at all
+                if chars.next_if_eq(&separator).is_some() {
+                    regex.push_str(&format!("(?:.*{escaped})?"));
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str(&format!("[^{escaped}]*")),
+            '?' => regex.push_str(&format!("[^{escaped}]")),
+            '[' => {
+                regex.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).with_context(|| format!("invalid glob '{pattern}'"))
+}
+
+/// Return the files matching a glob pattern, sorted by path.
+pub fn paths(pattern: &str) -> Result<Vec<PathBuf>> {
+    let regex = to_regex(pattern)?;
+    // Walk from the longest leading path without wildcards
+    let mut base = PathBuf::new();
+    let mut depth = 0;
+    let mut recursive = false;
+    for component in Path::new(pattern).components() {
+        let text = component.as_os_str().to_string_lossy();
+        if depth == 0 && !is_glob(&text) {
+            base.push(component);
+        } else {
+            depth += 1;
+            recursive |= text.contains("**");
+        }
+    }
+    let relative = base.as_os_str().is_empty();
+    let root = match relative {
+        true => PathBuf::from("."),
+        false => base,
+    };
+
+    let mut matches = vec![];
+    let mut stack = vec![(root, 0)];
+    while let Some((dir, level)) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // Match paths as written in the pattern
+            let display = match relative {
+                true => path.strip_prefix(".").unwrap_or(&path).to_owned(),
+                false => path.clone(),
+            };
+            if path.is_dir() {
+                if recursive || level + 1 < depth {
+                    stack.push((path, level + 1));
+                }
+            } else if regex.is_match(&display.to_string_lossy()) {
+                matches.push(display);
+            }
+        }
+    }
+    matches.sort();
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchors_path_globs() -> Result<()> {
+        let regex = to_regex("inventory.d/*.yml")?;
+        assert!(regex.is_match("inventory.d/web.yml"));
+        assert!(!regex.is_match("inventory.d/web.yml.bak"));
+        assert!(!regex.is_match("old/inventory.d/web.yml"));
+        assert!(!regex.is_match("inventory.d/teams/web.yml"));
+        Ok(())
+    }
+
+    #[test]
+    fn matches_across_components() -> Result<()> {
+        let regex = to_regex("teams/**/*.yml")?;
+        assert!(regex.is_match("teams/web.yml"));
+        assert!(regex.is_match("teams/a/b/web.yml"));
+        assert!(!regex.is_match("teams.yml"));
+        let regex = to_regex("site-?.[!x]ml")?;
+        assert!(regex.is_match("site-1.yml"));
+        assert!(!regex.is_match("site-1.xml"));
+        assert!(!regex.is_match("site-10.yml"));
+        Ok(())
+    }
+
+    #[test]
+    fn anchors_hostname_globs() -> Result<()> {
+        let regex = to_hostname_regex("*.imbleau.com")?;
+        assert!(regex.is_match("www.imbleau.com"));
+        assert!(regex.is_match("WWW.Imbleau.com"));
+        assert!(!regex.is_match("imbleau.com"));
+        assert!(!regex.is_match("a.www.imbleau.com"));
+        assert!(!regex.is_match("www.imbleau.com.evil.com"));
+        assert!(!regex.is_match("wwwximbleau.com"));
+        let regex = to_hostname_regex("**.imbleau.com")?;
+        assert!(regex.is_match("a.www.imbleau.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn finds_matching_paths() -> Result<()> {
+        let dir = std::env::temp_dir()
+            .join(format!("cddns-glob-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("teams/web"))?;
+        for file in ["a.yml", "b.json", "teams/c.yml", "teams/web/d.yml"] {
+            std::fs::write(dir.join(file), "")?;
+        }
+        let base = dir.to_string_lossy();
+        let found = paths(&format!("{base}/*.yml"));
+        let nested = paths(&format!("{base}/**/*.yml"));
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(found?, vec![dir.join("a.yml")]);
+        assert_eq!(
+            nested?,
+            vec![
+                dir.join("a.yml"),
+                dir.join("teams/c.yml"),
+                dir.join("teams/web/d.yml")
+            ]
+        );
+        Ok(())
+    }
+}
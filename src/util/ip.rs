@@ -0,0 +1,153 @@
+use crate::cloudflare::models::Record;
+use anyhow::{bail, Context, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use tracing::trace;
+
+/// A lazy cache of the host's public IP addresses, resolved at most once.
+#[derive(Debug, Default, Clone)]
+pub struct PublicIps {
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+}
+
+impl PublicIps {
+    /// Return the public IPv4 address, resolving it if necessary.
+    pub async fn ipv4(&mut self) -> Result<Ipv4Addr> {
+        match self.ipv4 {
+            Some(ip) => Ok(ip),
+            None => {
+                trace!("resolving ipv4...");
+                let ip = public_ip::addr_v4().await.context(
+                    "could not resolve public ipv4 needed for A record",
+                )?;
+                Ok(*self.ipv4.insert(ip))
+            }
+        }
+    }
+
+    /// Return the public IPv6 address, resolving it if necessary.
+    pub async fn ipv6(&mut self) -> Result<Ipv6Addr> {
+        match self.ipv6 {
+            Some(ip) => Ok(ip),
+            None => {
+                trace!("resolving ipv6...");
+                let ip = public_ip::addr_v6().await.context(
+                    "could not resolve public ipv6 needed for AAAA record",
+                )?;
+                Ok(*self.ipv6.insert(ip))
+            }
+        }
+    }
+
+    /// Return the public IP address a record is expected to point to.
+    pub async fn for_record(&mut self, record: &Record) -> Result<String> {
+        match record.record_type.as_str() {
+            "A" => Ok(self.ipv4().await?.to_string()),
+            "AAAA" => Ok(self.ipv6().await?.to_string()),
+            other => bail!("unsupported record type '{other}'"),
+        }
+    }
+}
+
+/// An IP network in CIDR notation, e.g. `192.0.2.0/24` or `2001:db8::/32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Returns whether an address is within the network.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32);
+                let mask = mask.unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32);
+                let mask = mask.unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (network, prefix) = s.split_once('/').with_context(|| {
+            format!("invalid CIDR '{s}', expected a prefix length")
+        })?;
+        let network = network.parse::<IpAddr>().with_context(|| {
+            format!("invalid CIDR '{s}', expected an IP address")
+        })?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix| *prefix <= max)
+            .with_context(|| {
+                format!(
+                    "invalid CIDR '{s}', expected a prefix length up to {max}"
+                )
+            })?;
+        Ok(Self { network, prefix })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(cidr: &str, ip: &str) -> Result<bool> {
+        Ok(cidr.parse::<Cidr>()?.contains(ip.parse()?))
+    }
+
+    #[test]
+    fn contains_ipv4() -> Result<()> {
+        assert!(contains("192.0.2.0/24", "192.0.2.255")?);
+        assert!(!contains("192.0.2.0/24", "192.0.3.0")?);
+        assert!(contains("192.0.2.1/32", "192.0.2.1")?);
+        assert!(!contains("192.0.2.1/32", "192.0.2.2")?);
+        assert!(contains("0.0.0.0/0", "203.0.113.7")?);
+        Ok(())
+    }
+
+    #[test]
+    fn contains_ipv6() -> Result<()> {
+        assert!(contains("2001:db8::/32", "2001:db8:ffff::1")?);
+        assert!(!contains("2001:db8::/32", "2001:db9::1")?);
+        assert!(contains("::/0", "2001:db8::1")?);
+        assert!(contains("2001:db8::1/128", "2001:db8::1")?);
+        assert!(!contains("2001:db8::1/128", "2001:db8::2")?);
+        // Prefixes which split a group
+        assert!(contains("2001:db8:8000::/33", "2001:db8:ffff::1")?);
+        assert!(!contains("2001:db8:8000::/33", "2001:db8:7fff::1")?);
+        Ok(())
+    }
+
+    #[test]
+    fn never_mixes_families() -> Result<()> {
+        assert!(!contains("0.0.0.0/0", "::ffff:192.0.2.1")?);
+        assert!(!contains("::/0", "192.0.2.1")?);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_cidrs() {
+        for cidr in [
+            "192.0.2.0",
+            "192.0.2.0/33",
+            "2001:db8::/129",
+            "www/24",
+            "::/-1",
+        ] {
+            assert!(cidr.parse::<Cidr>().is_err(), "{cidr}");
+        }
+    }
+}
@@ -1,6 +1,14 @@
 //! cddns utility and helper functions.
 
 pub mod encoding;
+pub mod exit;
 pub mod fs;
+pub mod glob;
+pub mod ip;
+pub mod pattern;
 pub mod postprocessors;
 pub mod scanner;
+pub mod stdout;
+pub mod table;
+pub mod template;
+pub mod tui;
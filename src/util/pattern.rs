@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The syntax of `list` filter patterns without a `glob:` or `regex:`
+/// prefix.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternSyntax {
+    /// Regular expressions, e.g. `.*\.example\.com`.
+    #[default]
+    Regex,
+    /// Hostname globs, e.g. `*.example.com`, which always match whole values.
+    Glob,
+}
+
+/// A compiled filter pattern for zone and record names and ids.
+#[derive(Clone, Debug)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    /// Compile a pattern. A `glob:` or `regex:` prefix picks the syntax,
+    /// otherwise the default syntax is used. Regexes are anchored to match
+    /// whole values when `anchored` is set.
+    pub fn new(
+        pattern: &str,
+        syntax: PatternSyntax,
+        anchored: bool,
+    ) -> Result<Self> {
+        let (syntax, body) = if let Some(glob) = pattern.strip_prefix("glob:") {
+            (PatternSyntax::Glob, glob)
+        } else if let Some(regex) = pattern.strip_prefix("regex:") {
+            (PatternSyntax::Regex, regex)
+        } else {
+            (syntax, pattern)
+        };
+        let regex = match syntax {
+            PatternSyntax::Glob => crate::util::glob::to_hostname_regex(body),
+            PatternSyntax::Regex if anchored => {
+                Regex::new(&format!("^(?:{body})$")).map_err(Into::into)
+            }
+            PatternSyntax::Regex => Regex::new(body).map_err(Into::into),
+        };
+        regex
+            .map(Self)
+            .with_context(|| format!("compiling pattern '{pattern}'"))
+    }
+
+    /// Returns whether the pattern matches a value.
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(
+        pattern: &str,
+        syntax: PatternSyntax,
+        anchored: bool,
+        value: &str,
+    ) -> Result<bool> {
+        Ok(Pattern::new(pattern, syntax, anchored)?.is_match(value))
+    }
+
+    #[test]
+    fn uses_default_syntax() -> Result<()> {
+        let (regex, glob) = (PatternSyntax::Regex, PatternSyntax::Glob);
+        assert!(matches("imbleau", regex, false, "www.imbleau.com")?);
+        assert!(!matches("imbleau", glob, false, "www.imbleau.com")?);
+        assert!(matches("*.imbleau.com", glob, false, "www.imbleau.com")?);
+        assert!(Pattern::new("*.imbleau.com", regex, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn prefixes_pick_the_syntax() -> Result<()> {
+        let regex = PatternSyntax::Regex;
+        assert!(matches(
+            "glob:*.imbleau.com",
+            regex,
+            false,
+            "www.imbleau.com"
+        )?);
+        assert!(matches(
+            "regex:^www\\.",
+            PatternSyntax::Glob,
+            false,
+            "www.imbleau.com"
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn anchors_regexes() -> Result<()> {
+        let regex = PatternSyntax::Regex;
+        assert!(matches("www|api", regex, false, "www.imbleau.com")?);
+        assert!(!matches("www|api", regex, true, "www.imbleau.com")?);
+        assert!(matches("www|api", regex, true, "api")?);
+        assert!(matches(
+            ".*\\.imbleau\\.com",
+            regex,
+            true,
+            "www.imbleau.com"
+        )?);
+        // Globs always match whole values
+        assert!(!matches(
+            "glob:www.imbleau.com",
+            regex,
+            false,
+            "www.imbleau.com.evil.com"
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_invalid_patterns() {
+        let err = Pattern::new("regex:(", PatternSyntax::Glob, false)
+            .map_err(|err| err.to_string());
+        assert_eq!(err.err().as_deref(), Some("compiling pattern 'regex:('"));
+    }
+}
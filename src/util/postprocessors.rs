@@ -1,7 +1,8 @@
 use crate::cloudflare;
 use crate::cloudflare::models::{Record, Zone};
 use crate::config::models::ConfigOpts;
-use crate::inventory::models::InventoryData;
+use crate::inventory::editor::InventoryEditor;
+use crate::inventory::models::InventoryFormat;
 use anyhow::{Context, Result};
 use chrono::Local;
 use tracing::{trace, warn};
@@ -11,21 +12,37 @@ pub trait PostProcessor {
     fn post_process(&self, contents: &mut String) -> Result<()>;
 }
 
-/// A post-processor prefixes a timestamp header to the beginning of the data.
+/// The first line of the header prefixed by [`TimestampPostProcessor`].
+const GENERATED_HEADER: &str =
+    "# This file was automatically @generated by cddns.";
+
+/// A post-processor prefixes a timestamp header to the beginning of the data,
+/// replacing any previous header.
 pub struct TimestampPostProcessor;
 impl PostProcessor for TimestampPostProcessor {
     fn post_process(&self, contents: &mut String) -> Result<()> {
         trace!("starting post-processing: timestamp");
+        // Remove a previous header
+        if contents.starts_with(GENERATED_HEADER) {
+            let mut lines = contents.lines().skip(1).peekable();
+            lines.next_if(|l| l.starts_with("# last-modified:"));
+            lines.next_if(|l| l.is_empty());
+            let mut stripped = lines.collect::<Vec<&str>>().join("\n");
+            if contents.ends_with('\n') {
+                stripped.push('\n');
+            }
+            *contents = stripped;
+        }
         // Inject header
         let header = format!(
-            r#"# This file was automatically @generated by cddns.
+            r#"{GENERATED_HEADER}
 # last-modified: {}
 
 "#,
             Local::now()
         );
         contents.insert_str(0, &header);
-        trace!("finished post-processing: inventory aliases");
+        trace!("finished post-processing: timestamp");
         Ok(())
     }
 }
@@ -35,10 +52,14 @@ impl PostProcessor for TimestampPostProcessor {
 pub struct InventoryAliasCommentPostProcessor {
     zones: Vec<Zone>,
     records: Vec<Record>,
+    format: InventoryFormat,
 }
 impl InventoryAliasCommentPostProcessor {
     /// Initialize the inventory alias post-processor.
-    pub async fn try_init(opts: &ConfigOpts) -> Result<Self> {
+    pub async fn try_init(
+        opts: &ConfigOpts,
+        format: InventoryFormat,
+    ) -> Result<Self> {
         trace!("starting data retrieval for cloudflare post-processing");
         let token = opts
                     .verify.token.as_ref()
@@ -46,72 +67,36 @@ impl InventoryAliasCommentPostProcessor {
         let zones = cloudflare::endpoints::zones(&token).await?;
         let records = cloudflare::endpoints::records(&zones, &token).await?;
         trace!("finished retrieval of cloudflare post-processing resources");
-        Ok(InventoryAliasCommentPostProcessor::from(zones, records))
+        Ok(InventoryAliasCommentPostProcessor::from(
+            zones, records, format,
+        ))
     }
 
-    pub fn from(zones: Vec<Zone>, records: Vec<Record>) -> Self {
-        Self { zones, records }
+    pub fn from(
+        zones: Vec<Zone>,
+        records: Vec<Record>,
+        format: InventoryFormat,
+    ) -> Self {
+        Self {
+            zones,
+            records,
+            format,
+        }
     }
 }
 
 impl PostProcessor for InventoryAliasCommentPostProcessor {
-    fn post_process(&self, yaml: &mut String) -> Result<()> {
+    fn post_process(&self, contents: &mut String) -> Result<()> {
         trace!("starting post-processing: inventory aliases");
-        let data = serde_yaml::from_slice::<InventoryData>(yaml.as_bytes())
-            .context("deserializing inventory from bytes")?;
-
-        for (zone_id, record_ids) in data.into_iter() {
-            // Post-process zone
-            if let Some(zone) =
-                crate::cmd::list::find_zone(&self.zones, &zone_id)
-            {
-                let z_idx =
-                    yaml.find(&zone_id).context("zone not found in yaml")?;
-                yaml.insert_str(
-                    z_idx + zone_id.len() + ":".len(),
-                    &format!(
-                        " # '{}'",
-                        if zone_id == zone.id {
-                            zone.name
-                        } else {
-                            zone.id
-                        }
-                    ),
-                );
-            } else {
-                warn!(
-                    "post-processing '{}' failed: cloudflare zone not found",
-                    zone_id
-                );
-            }
-
-            // Post-process records
-            for record_id in record_ids {
-                if let Some(record) =
-                    crate::cmd::list::find_record(&self.records, &record_id)
-                {
-                    let r_idx = yaml
-                        .find(&record_id)
-                        .context("record not found in yaml")?;
-                    yaml.insert_str(
-                        r_idx + record_id.len(),
-                        &format!(
-                            " # '{}'",
-                            if record_id == record.id {
-                                record.name
-                            } else {
-                                record.id
-                            }
-                        ),
-                    );
-                } else {
-                    warn!(
-                    "post-processing '{}' failed: cloudflare record not found",
-                    record_id
-                );
-                }
-            }
+        let mut editor = InventoryEditor::parse(contents, self.format)
+            .context("parsing inventory structure")?;
+        for unresolved in editor.annotate(&self.zones, &self.records) {
+            warn!(
+                "post-processing '{}' failed: cloudflare resource not found",
+                unresolved
+            );
         }
+        *contents = editor.to_string();
         trace!("finished post-processing: inventory aliases");
         Ok(())
     }
@@ -0,0 +1,15 @@
+use anyhow::{Context, Result};
+use std::fmt::Display;
+use std::io::{ErrorKind, Write};
+
+/// Print to stdout, exiting cleanly if it was closed, e.g. when piped to
+/// `head`, where `print!` would panic.
+pub fn print(text: impl Display) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match write!(stdout, "{text}").and_then(|()| stdout.flush()) {
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => {
+            std::process::exit(0)
+        }
+        result => result.context("writing to stdout"),
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{bail, Result};
+use std::fmt::Write;
+
+/// Rows of named columns, printed as an aligned table, as CSV, or with a
+/// template per row.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Create an empty table with the given columns.
+    pub fn new(columns: &[&'static str]) -> Self {
+        Self {
+            columns: columns.to_vec(),
+            rows: vec![],
+        }
+    }
+
+    /// Add a row, with a value for each column.
+    pub fn push(&mut self, row: Vec<String>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    /// Return the table with aligned columns and a header.
+    pub fn to_aligned(&self) -> String {
+        let header = self
+            .columns
+            .iter()
+            .map(|column| column.to_uppercase())
+            .collect::<Vec<String>>();
+        let mut widths = header.iter().map(String::len).collect::<Vec<_>>();
+        for row in self.rows.iter() {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+        let mut table = String::new();
+        for row in std::iter::once(&header).chain(self.rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(value, width)| format!("{value:width$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            let _ = writeln!(table, "{}", line.trim_end());
+        }
+        table
+    }
+
+    /// Return the table as CSV with a header, quoting values as needed.
+    pub fn to_csv(&self) -> String {
+        let quote = |value: &str| {
+            if value.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_string()
+            }
+        };
+        let mut csv = String::new();
+        let header = self.columns.iter().map(|c| c.to_string());
+        let _ = writeln!(csv, "{}", header.collect::<Vec<_>>().join(","));
+        for row in self.rows.iter() {
+            let line = row.iter().map(|v| quote(v)).collect::<Vec<_>>();
+            let _ = writeln!(csv, "{}", line.join(","));
+        }
+        csv
+    }
+
+    /// Return a line per row, replacing each `{column}` in the template with
+    /// its value. `{{` and `}}` are a literal `{` and `}`.
+    pub fn to_template(&self, template: &str) -> Result<String> {
+        // Validate the template, even without rows
+        self.render(template, &vec![String::new(); self.columns.len()])?;
+        let mut lines = String::new();
+        for row in self.rows.iter() {
+            let _ = writeln!(lines, "{}", self.render(template, row)?);
+        }
+        Ok(lines)
+    }
+
+    /// Render a template for a single row.
+    fn render(&self, template: &str, row: &[String]) -> Result<String> {
+        let mut line = String::new();
+        let mut rest = template;
+        while let Some(idx) = rest.find(['{', '}']) {
+            line.push_str(&rest[..idx]);
+            rest = &rest[idx..];
+            if let Some(after) = rest.strip_prefix("{{") {
+                line.push('{');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("}}") {
+                line.push('}');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('{') {
+                let Some(end) = after.find('}') else {
+                    bail!("unterminated field in template '{template}', expected '}}'");
+                };
+                let name = &after[..end];
+                let Some(column) = self.columns.iter().position(|c| *c == name)
+                else {
+                    bail!(
+                        "unknown field '{name}' in template, expected one of: {}",
+                        self.columns.join(", ")
+                    );
+                };
+                line.push_str(&row[column]);
+                rest = &after[end + 1..];
+            } else {
+                bail!("unmatched '}}' in template '{template}', use '}}}}' for a literal '}}'");
+            }
+        }
+        line.push_str(rest);
+        Ok(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["name", "content"]);
+        table.push(vec!["www.imbleau.com".to_string(), "1.2.3.4".to_string()]);
+        table.push(vec!["txt.imbleau.com".to_string(), "a, \"b\"".to_string()]);
+        table
+    }
+
+    #[test]
+    fn aligns_columns() {
+        assert_eq!(
+            table().to_aligned(),
+            "\
+NAME             CONTENT
+www.imbleau.com  1.2.3.4
+txt.imbleau.com  a, \"b\"
+"
+        );
+    }
+
+    #[test]
+    fn quotes_csv_values() {
+        let mut table = table();
+        table.push(vec!["multi".to_string(), "a\nb".to_string()]);
+        assert_eq!(
+            table.to_csv(),
+            "\
+name,content
+www.imbleau.com,1.2.3.4
+txt.imbleau.com,\"a, \"\"b\"\"\"
+multi,\"a\nb\"
+"
+        );
+    }
+
+    #[test]
+    fn renders_templates() -> Result<()> {
+        assert_eq!(
+            table().to_template("{name}={content}")?,
+            "www.imbleau.com=1.2.3.4\ntxt.imbleau.com=a, \"b\"\n"
+        );
+        assert_eq!(
+            table().to_template("{{{name}}} }}")?,
+            "{www.imbleau.com} }\n{txt.imbleau.com} }\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        // Templates are checked even without rows
+        let empty = Table::new(&["name"]);
+        let error = |template| {
+            empty
+                .to_template(template)
+                .map_err(|err| err.to_string())
+                .err()
+        };
+        assert_eq!(
+            error("{type}").as_deref(),
+            Some("unknown field 'type' in template, expected one of: name")
+        );
+        assert_eq!(
+            error("{name").as_deref(),
+            Some("unterminated field in template '{name', expected '}'")
+        );
+        assert_eq!(
+            error("name}").as_deref(),
+            Some(
+                "unmatched '}' in template 'name}', use '}}' for a literal '}'"
+            )
+        );
+    }
+}
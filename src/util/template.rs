@@ -0,0 +1,137 @@
+use anyhow::{bail, Context, Result};
+
+/// Expand the placeholders in a string.
+///
+/// `${VAR}` expands to an environment variable, which must be set, and
+/// `${VAR:-default}` falls back to a default if the variable is unset or
+/// empty. `{hostname}` expands to the short, lowercase hostname of this
+/// machine, and `$$` to a literal `$`.
+pub fn expand(text: &str) -> Result<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find(['$', '{']) {
+        expanded.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        if let Some(after) = rest.strip_prefix("{hostname}") {
+            expanded.push_str(&hostname()?);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("$$") {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let Some(end) = after.find('}') else {
+                bail!("unterminated variable in '{text}', expected '}}'");
+            };
+            expanded.push_str(&variable(&after[..end], text)?);
+            rest = &after[end + 1..];
+        } else {
+            // A lone `$` or `{` is kept as is
+            expanded.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expand a `NAME` or `NAME:-default` variable expression.
+fn variable(expression: &str, text: &str) -> Result<String> {
+    let (name, default) = match expression.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (expression, None),
+    };
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("invalid variable name '{name}' in '{text}'");
+    }
+    match (std::env::var(name), default) {
+        (Ok(value), Some(default)) if value.is_empty() => {
+            Ok(default.to_string())
+        }
+        (Ok(value), _) => Ok(value),
+        (Err(_), Some(default)) => Ok(default.to_string()),
+        (Err(std::env::VarError::NotUnicode(_)), None) => {
+            bail!("environment variable '{name}' is not valid unicode, in '{text}'")
+        }
+        (Err(std::env::VarError::NotPresent), None) => {
+            bail!("environment variable '{name}' is not set, in '{text}', use '${{{name}:-default}}' for a default")
+        }
+    }
+}
+
+/// Return the short, lowercase hostname of this machine.
+fn hostname() -> Result<String> {
+    let hostname = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .context("could not determine the hostname for '{hostname}', set the HOSTNAME environment variable")?;
+    let short = hostname.split('.').next().unwrap_or(&hostname);
+    Ok(short.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_variables() -> Result<()> {
+        std::env::set_var("CDDNS_TEST_TEMPLATE_SITE", "paris");
+        std::env::set_var("CDDNS_TEST_TEMPLATE_EMPTY", "");
+        assert_eq!(
+            expand("www.${CDDNS_TEST_TEMPLATE_SITE}.imbleau.com")?,
+            "www.paris.imbleau.com"
+        );
+        assert_eq!(
+            expand("${CDDNS_TEST_TEMPLATE_SITE:-lyon}.imbleau.com")?,
+            "paris.imbleau.com"
+        );
+        // Defaults apply to unset and empty variables
+        assert_eq!(
+            expand("${CDDNS_TEST_TEMPLATE_UNSET:-lyon}.imbleau.com")?,
+            "lyon.imbleau.com"
+        );
+        assert_eq!(
+            expand("${CDDNS_TEST_TEMPLATE_EMPTY:-lyon}.imbleau.com")?,
+            "lyon.imbleau.com"
+        );
+        assert_eq!(expand("${CDDNS_TEST_TEMPLATE_UNSET:-}")?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_literals() -> Result<()> {
+        assert_eq!(expand("www.imbleau.com")?, "www.imbleau.com");
+        assert_eq!(
+            expand("$${CDDNS_TEST_TEMPLATE_UNSET}")?,
+            "${CDDNS_TEST_TEMPLATE_UNSET}"
+        );
+        assert_eq!(expand("a$b{c}$")?, "a$b{c}$");
+        assert_eq!(expand("$$$$")?, "$$");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_variables() {
+        let error = |text| expand(text).map_err(|err| err.to_string()).err();
+        assert_eq!(
+            error("${CDDNS_TEST_TEMPLATE_UNSET}").as_deref(),
+            Some("environment variable 'CDDNS_TEST_TEMPLATE_UNSET' is not set, in '${CDDNS_TEST_TEMPLATE_UNSET}', use '${CDDNS_TEST_TEMPLATE_UNSET:-default}' for a default")
+        );
+        assert_eq!(
+            error("www.${SITE").as_deref(),
+            Some("unterminated variable in 'www.${SITE', expected '}'")
+        );
+        assert_eq!(
+            error("${1SITE}").as_deref(),
+            Some("invalid variable name '1SITE' in '${1SITE}'")
+        );
+        assert!(error("${}").is_some());
+    }
+}
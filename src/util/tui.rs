@@ -0,0 +1,574 @@
+use crate::cloudflare::models::{Record, Zone};
+use crate::cmd::inventory::RecordStatus;
+use crate::inventory::models::{InventoryData, InventoryRecord};
+use anyhow::Result;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+};
+use crossterm::style::{
+    Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor,
+};
+use crossterm::terminal::{
+    self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::collections::HashMap;
+use std::io::{Stdout, Write};
+
+/// The result of an inventory terminal UI session.
+pub enum TuiOutcome {
+    /// The user saved, yielding the resulting inventory data.
+    Save(InventoryData),
+    /// The user quit without saving.
+    Quit,
+}
+
+/// A full-screen terminal UI to build and review inventories, presenting a
+/// collapsible zone to record tree with checkboxes.
+pub struct InventoryTui {
+    title: String,
+    original: InventoryData,
+    zones: Vec<ZoneNode>,
+    cursor: usize,
+    scroll: usize,
+    filter: String,
+    filtering: bool,
+    dirty: bool,
+    message: Option<String>,
+}
+
+struct ZoneNode {
+    id: String,
+    name: String,
+    expanded: bool,
+    records: Vec<RecordNode>,
+}
+
+struct RecordNode {
+    /// The zone identifier used in the inventory.
+    zone_key: String,
+    /// The record identifier used in the inventory.
+    key: String,
+    /// The inventory entry the record was listed as, if any.
+    entry: Option<InventoryRecord>,
+    name: String,
+    record_type: String,
+    content: String,
+    status: Option<RecordStatus>,
+    checked: bool,
+    /// Whether the record was present in the original inventory.
+    original: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Row {
+    Zone(usize),
+    Record(usize, usize),
+}
+
+/// Restores the terminal, even on early return.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(stdout: &mut Stdout) -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl InventoryTui {
+    /// Initialize the terminal UI. Records present in `data` start checked,
+    /// glob and regex entries are shown as patterns with the amount of records
+    /// they match, and other entries unknown to Cloudflare are shown as
+    /// invalid.
+    pub fn new(
+        title: impl Into<String>,
+        zones: &[Zone],
+        records: &[Record],
+        data: &InventoryData,
+        statuses: &HashMap<String, RecordStatus>,
+    ) -> Self {
+        let mut entries = data
+            .records()
+            .into_iter()
+            .flat_map(|(zone, records)| {
+                records
+                    .into_iter()
+                    .map(move |record| (zone.clone(), record))
+            })
+            .collect::<Vec<(String, InventoryRecord)>>();
+
+        let mut nodes = vec![];
+        for zone in zones {
+            // New records join the zone identifier already in use, if any
+            let zone_key = entries
+                .iter()
+                .map(|(z, _)| z)
+                .find(|z| **z == zone.id || **z == zone.name)
+                .unwrap_or(&zone.id)
+                .clone();
+            let mut node = ZoneNode {
+                id: zone.id.clone(),
+                name: zone.name.clone(),
+                expanded: false,
+                records: vec![],
+            };
+            for record in records.iter().filter(|r| r.zone_id == zone.id) {
+                let position = entries.iter().position(|(z, r)| {
+                    (*z == zone.id || *z == zone.name) && r.refers_to(record)
+                });
+                let (zone_key, key, entry) = match position {
+                    Some(idx) => {
+                        let (z, r) = entries.remove(idx);
+                        (z, r.key().to_string(), Some(r))
+                    }
+                    None => (zone_key.clone(), record.id.clone(), None),
+                };
+                let original = entry.is_some();
+                node.records.push(RecordNode {
+                    zone_key,
+                    key,
+                    entry,
+                    name: record.name.clone(),
+                    record_type: record.record_type.clone(),
+                    content: record.content.clone(),
+                    status: statuses.get(&record.id).copied(),
+                    checked: original,
+                    original,
+                });
+            }
+            nodes.push(node);
+        }
+
+        // Remaining entries are patterns, or unknown to Cloudflare
+        for (zone_key, entry) in entries {
+            let idx = match nodes
+                .iter()
+                .position(|z| z.id == zone_key || z.name == zone_key)
+            {
+                Some(idx) => idx,
+                None => {
+                    nodes.push(ZoneNode {
+                        id: zone_key.clone(),
+                        name: zone_key.clone(),
+                        expanded: false,
+                        records: vec![],
+                    });
+                    nodes.len() - 1
+                }
+            };
+            let record_type = match entry {
+                InventoryRecord::Glob { .. } => "glob",
+                InventoryRecord::Regex { .. } => "regex",
+                _ => "?",
+            };
+            let (content, status) = match entry.pattern() {
+                Ok(Some(pattern)) => {
+                    let matching = records
+                        .iter()
+                        .filter(|r| r.zone_id == nodes[idx].id)
+                        .filter(|r| pattern.is_match(&r.name))
+                        .count();
+                    (format!("{matching} matching"), None)
+                }
+                Ok(None) => (String::new(), Some(RecordStatus::Invalid)),
+                Err(_) => {
+                    ("invalid pattern".to_string(), Some(RecordStatus::Invalid))
+                }
+            };
+            nodes[idx].records.push(RecordNode {
+                zone_key,
+                name: entry.key().to_string(),
+                key: entry.key().to_string(),
+                entry: Some(entry),
+                record_type: record_type.to_string(),
+                content,
+                status,
+                checked: true,
+                original: true,
+            });
+        }
+        nodes.retain(|z| !z.records.is_empty());
+
+        Self {
+            title: title.into(),
+            original: data.clone(),
+            zones: nodes,
+            cursor: 0,
+            scroll: 0,
+            filter: String::new(),
+            filtering: false,
+            dirty: false,
+            message: None,
+        }
+    }
+
+    /// Run the terminal UI until the user saves or quits.
+    pub fn run(mut self) -> Result<TuiOutcome> {
+        let mut stdout = std::io::stdout();
+        let _guard = TerminalGuard::enter(&mut stdout)?;
+        loop {
+            self.draw(&mut stdout)?;
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    if let Some(outcome) = self.handle_key(key) {
+                        return Ok(outcome);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Return the inventory data as currently selected.
+    fn data(&self) -> InventoryData {
+        let mut data = self.original.clone();
+        for zone in self.zones.iter() {
+            for record in zone.records.iter() {
+                if record.checked && !record.original {
+                    data.insert(&record.zone_key, &record.key);
+                } else if !record.checked {
+                    if let Some(entry) = record.entry.as_ref() {
+                        data.remove_record(&record.zone_key, entry);
+                    }
+                }
+            }
+        }
+        data
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        text.to_lowercase().contains(&self.filter.to_lowercase())
+    }
+
+    /// Return the rows visible with the current filter and expansion.
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![];
+        for (z, zone) in self.zones.iter().enumerate() {
+            if self.filter.is_empty() {
+                rows.push(Row::Zone(z));
+                if zone.expanded {
+                    rows.extend(
+                        (0..zone.records.len()).map(|r| Row::Record(z, r)),
+                    );
+                }
+                continue;
+            }
+            // While filtering, matching records are always revealed
+            let zone_match = self.matches(&zone.name) || self.matches(&zone.id);
+            let records = zone
+                .records
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| {
+                    zone_match
+                        || self.matches(&r.name)
+                        || self.matches(&r.key)
+                        || self.matches(&r.content)
+                })
+                .map(|(r, _)| Row::Record(z, r))
+                .collect::<Vec<Row>>();
+            if zone_match || !records.is_empty() {
+                rows.push(Row::Zone(z));
+                if zone.expanded || !zone_match {
+                    rows.extend(records);
+                }
+            }
+        }
+        rows
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<TuiOutcome> {
+        self.message = None;
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && key.code == KeyCode::Char('c')
+        {
+            return Some(TuiOutcome::Quit);
+        }
+        if self.filtering {
+            match key.code {
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filtering = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.cursor = 0;
+            self.scroll = 0;
+            return None;
+        }
+
+        let rows = self.rows();
+        let page = Self::body_height();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.cursor = self.cursor.saturating_sub(1)
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.cursor += 1,
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(page),
+            KeyCode::PageDown => self.cursor += page,
+            KeyCode::Home | KeyCode::Char('g') => self.cursor = 0,
+            KeyCode::End | KeyCode::Char('G') => self.cursor = usize::MAX,
+            KeyCode::Left | KeyCode::Char('h') => match rows.get(self.cursor) {
+                Some(Row::Zone(z)) => self.zones[*z].expanded = false,
+                Some(Row::Record(z, _)) => {
+                    let z = *z;
+                    self.zones[z].expanded = false;
+                    if let Some(idx) = self
+                        .rows()
+                        .iter()
+                        .position(|r| matches!(r, Row::Zone(i) if *i == z))
+                    {
+                        self.cursor = idx;
+                    }
+                }
+                None => {}
+            },
+            KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(Row::Zone(z)) = rows.get(self.cursor) {
+                    self.zones[*z].expanded = true;
+                }
+            }
+            KeyCode::Enter => match rows.get(self.cursor) {
+                Some(Row::Zone(z)) => {
+                    self.zones[*z].expanded = !self.zones[*z].expanded
+                }
+                Some(row) => self.toggle(*row, &rows),
+                None => {}
+            },
+            KeyCode::Char(' ') => {
+                if let Some(row) = rows.get(self.cursor) {
+                    self.toggle(*row, &rows);
+                }
+            }
+            KeyCode::Char('a') => {
+                let visible = rows
+                    .iter()
+                    .filter_map(|row| match row {
+                        Row::Record(z, r) => Some((*z, *r)),
+                        Row::Zone(_) => None,
+                    })
+                    .collect::<Vec<(usize, usize)>>();
+                let check = visible
+                    .iter()
+                    .any(|(z, r)| !self.zones[*z].records[*r].checked);
+                for (z, r) in visible {
+                    self.zones[z].records[r].checked = check;
+                }
+                self.dirty = true;
+            }
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Char('s') => return Some(TuiOutcome::Save(self.data())),
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if !self.dirty {
+                    return Some(TuiOutcome::Quit);
+                }
+                self.message = Some(
+                    "unsaved changes, press 'Q' to discard or 's' to save"
+                        .to_string(),
+                );
+            }
+            KeyCode::Char('Q') => return Some(TuiOutcome::Quit),
+            _ => {}
+        }
+        self.cursor = self.cursor.min(self.rows().len().saturating_sub(1));
+        None
+    }
+
+    /// Toggle a checkbox. Toggling a zone toggles all of its visible records.
+    fn toggle(&mut self, row: Row, rows: &[Row]) {
+        match row {
+            Row::Record(z, r) => {
+                let record = &mut self.zones[z].records[r];
+                record.checked = !record.checked;
+            }
+            Row::Zone(z) => {
+                let children = rows
+                    .iter()
+                    .filter_map(|row| match row {
+                        Row::Record(i, r) if *i == z => Some(*r),
+                        _ => None,
+                    })
+                    .collect::<Vec<usize>>();
+                let children = if children.is_empty() {
+                    (0..self.zones[z].records.len()).collect()
+                } else {
+                    children
+                };
+                let check =
+                    children.iter().any(|r| !self.zones[z].records[*r].checked);
+                for r in children {
+                    self.zones[z].records[r].checked = check;
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn body_height() -> usize {
+        let (_, rows) = terminal::size().unwrap_or((80, 24));
+        (rows as usize).saturating_sub(4).max(1)
+    }
+
+    fn draw(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let (width, _) = terminal::size().unwrap_or((80, 24));
+        let width = width as usize;
+        let height = Self::body_height();
+        let rows = self.rows();
+
+        // Keep the cursor within the viewport
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+
+        queue!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+
+        // Header
+        let selected = self
+            .zones
+            .iter()
+            .flat_map(|z| z.records.iter())
+            .filter(|r| r.checked)
+            .count();
+        let header = format!(
+            "{} - {selected} selected{}",
+            self.title,
+            if self.dirty { " (modified)" } else { "" }
+        );
+        queue!(
+            stdout,
+            SetAttribute(Attribute::Bold),
+            Print(truncate(&header, width)),
+            SetAttribute(Attribute::Reset),
+            MoveTo(0, 1),
+        )?;
+        let filter = if self.filtering {
+            format!("filter: {}_", self.filter)
+        } else if self.filter.is_empty() {
+            "filter: (press / to filter)".to_string()
+        } else {
+            format!("filter: {}", self.filter)
+        };
+        queue!(stdout, Print(truncate(&filter, width)))?;
+
+        // Body
+        let name_width = self
+            .zones
+            .iter()
+            .flat_map(|z| z.records.iter())
+            .map(|r| r.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(width / 2);
+        for (line, (idx, row)) in rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .enumerate()
+        {
+            queue!(stdout, MoveTo(0, line as u16 + 2))?;
+            if idx == self.cursor {
+                queue!(stdout, SetAttribute(Attribute::Reverse))?;
+            }
+            match *row {
+                Row::Zone(z) => {
+                    let zone = &self.zones[z];
+                    let checked =
+                        zone.records.iter().filter(|r| r.checked).count();
+                    let checkbox = if checked == 0 {
+                        "[ ]"
+                    } else if checked == zone.records.len() {
+                        "[x]"
+                    } else {
+                        "[-]"
+                    };
+                    let open = rows.get(idx + 1).is_some_and(
+                        |r| matches!(r, Row::Record(i, _) if *i == z),
+                    );
+                    let arrow = if open { 'v' } else { '>' };
+                    let text = if zone.id == zone.name {
+                        format!("{arrow} {checkbox} {}", zone.name)
+                    } else {
+                        format!("{arrow} {checkbox} {}: {}", zone.name, zone.id)
+                    };
+                    queue!(stdout, Print(truncate(&text, width)))?;
+                }
+                Row::Record(z, r) => {
+                    let record = &self.zones[z].records[r];
+                    let text = format!(
+                        "    [{}] {:name_width$}  {:4}  {}",
+                        if record.checked { 'x' } else { ' ' },
+                        record.name,
+                        record.record_type,
+                        record.content,
+                    );
+                    let text = truncate(&text, width.saturating_sub(10));
+                    let pattern = record
+                        .entry
+                        .as_ref()
+                        .is_some_and(InventoryRecord::is_pattern);
+                    let (status, color) = match record.status {
+                        None if pattern => ("pattern", Color::Cyan),
+                        Some(RecordStatus::Valid) => ("valid", Color::Green),
+                        Some(RecordStatus::Outdated) => {
+                            ("outdated", Color::Yellow)
+                        }
+                        Some(RecordStatus::Invalid) => ("invalid", Color::Red),
+                        None => ("-", Color::DarkGrey),
+                    };
+                    queue!(
+                        stdout,
+                        Print(&text),
+                        Print("  "),
+                        SetForegroundColor(color),
+                        Print(status),
+                        ResetColor,
+                    )?;
+                }
+            }
+            queue!(stdout, SetAttribute(Attribute::Reset))?;
+        }
+        if rows.is_empty() {
+            queue!(stdout, MoveTo(0, 2), Print("  (no matches)"))?;
+        }
+
+        // Footer
+        let footer = match self.message.as_ref() {
+            Some(message) => message.clone(),
+            None => "up/down move  left/right collapse/expand  space check  \
+                     a check all  / filter  s save  q quit"
+                .to_string(),
+        };
+        queue!(
+            stdout,
+            MoveTo(0, height as u16 + 3),
+            SetAttribute(Attribute::Dim),
+            Print(truncate(&footer, width)),
+            SetAttribute(Attribute::Reset),
+        )?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Truncate a string to a maximum amount of characters.
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}